@@ -0,0 +1,99 @@
+use crypto::pbkdf2::{pbkdf2_simple, pbkdf2_check};
+use rand::{OsRng, Rng};
+use rustc_serialize::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use game_state::Void;
+use responses::error_messages::*;
+
+const PBKDF2_ROUNDS: u32 = 10000;
+
+/// Registered accounts, persisted as a json map of account name to password hash,
+/// plus the session tokens handed out since the server was started.
+pub struct AccountStore {
+    path:     PathBuf,
+    accounts: BTreeMap<String, String>,
+    sessions: HashMap<String, String>,
+}
+
+impl AccountStore {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        info!("Opening account storage {}.", path.display());
+        let accounts = match File::open(path) {
+            Ok(mut file) => {
+                let mut content = String::new();
+                try!(file.read_to_string(&mut content));
+                try!(json::decode(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        debug!("Loaded {} accounts.", accounts.len());
+        Ok(AccountStore {
+            path:     path.to_path_buf(),
+            accounts: accounts,
+            sessions: HashMap::new(),
+        })
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.accounts.contains_key(name)
+    }
+
+    pub fn register(&mut self, name: &str, password: &str) -> Result<String, &'static str> {
+        info!("Registering account {}.", name);
+        if self.is_registered(name) {
+            error!("Account {} already exists.", name);
+            return Err(ACCOUNT_ALREADY_EXISTS);
+        }
+        let hash = try!(pbkdf2_simple(password, PBKDF2_ROUNDS).map_err(|_| CATASTROPHIC_FUCKUP));
+        self.accounts.insert(name.to_owned(), hash);
+        if let Err(e) = self.save() {
+            error!("Could not save account storage: {}.", e);
+            self.accounts.remove(name);
+            return Err(ACCOUNT_NOT_SAVED);
+        }
+        self.new_session(name)
+    }
+
+    pub fn login(&mut self, name: &str, password: &str) -> Result<String, &'static str> {
+        info!("Login attempt for account {}.", name);
+        let valid = match self.accounts.get(name) {
+            Some(hash) => pbkdf2_check(password, hash).unwrap_or(false),
+            None       => false,
+        };
+        match valid {
+            true  => self.new_session(name),
+            false => {
+                error!("Invalid credentials for account {}.", name);
+                Err(INVALID_CREDENTIALS)
+            }
+        }
+    }
+
+    pub fn session_account(&self, token: &str) -> Option<&str> {
+        self.sessions.get(token).map(|n| n.as_str())
+    }
+
+    fn new_session(&mut self, name: &str) -> Result<String, &'static str> {
+        let mut rng = try!(OsRng::new().map_err(|_| CATASTROPHIC_FUCKUP));
+        let token = rng.gen_iter::<u8>().take(16).map(|b| format!("{:02x}", b)).collect::<String>();
+        debug!("New session for account {}.", name);
+        self.sessions.insert(token.clone(), name.to_owned());
+        Ok(token)
+    }
+
+    fn save(&self) -> io::Result<Void> {
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut file = try!(File::create(&tmp_path));
+            try!(file.write_all(json::encode(&self.accounts).expect(CATASTROPHIC_FUCKUP).as_bytes()));
+            try!(file.sync_all());
+        }
+        fs::rename(tmp_path, &self.path)
+    }
+}
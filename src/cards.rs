@@ -1,7 +1,8 @@
 use rand;
-use rand::{Rng};
+use rand::{ChaChaRng, Rng, SeedableRng};
 
 use std::collections::HashSet;
+use game_state::Seat;
 
 #[derive(Clone, PartialEq, Eq, Hash, Copy)]
 pub enum Color {
@@ -36,11 +37,13 @@ impl Number {
     }
 }
 
-#[derive(RustcDecodable, RustcEncodable, PartialEq, Clone, Copy)]
-pub struct Card {
-    pub id:     usize,
-    pub color:  Color,
-    pub number: Number,
+protocol_struct! {
+    #[derive(RustcDecodable, RustcEncodable, PartialEq, Clone, Copy)]
+    pub struct Card {
+        pub id:     usize,
+        pub color:  Color,
+        pub number: Number,
+    }
 }
 
 impl Card {
@@ -53,28 +56,61 @@ impl Card {
     }
 }
 
-pub fn new_deck() -> Vec<Card> {
+/// The key material a deck is shuffled with. It is long enough that nobody can search for the seed
+/// that matches the cards seen so far, and it never leaves the server.
+pub type Seed = [u32; 8];
+
+pub fn new_seed() -> Seed {
+    rand::random()
+}
+
+/// Shuffles a full deck, the same seed always results in the same deck. The card ids are shuffled
+/// separately from the cards, so neither an id nor the order of ids tells anything about a card.
+pub fn new_deck(seed: &Seed) -> Vec<Card> {
     let mut cards: Vec<Card> = Vec::with_capacity(50);
-    let mut id = 1;
     let number_arity = &[(3, Number::One), (2, Number::Two), (2, Number::Three), (2, Number::Four), (1, Number::Five)];
     for color in &[Color::Blue, Color::Green, Color::Red, Color::White, Color::Yellow] {
         for &(amount, ref number) in number_arity {
             for _ in 0..amount {
-                cards.push(Card::new(id, *color, *number));
-                id += 1;
+                cards.push(Card::new(0, *color, *number));
             }
         }
     }
-    rand::thread_rng().shuffle(&mut cards);
+    let mut rng = ChaChaRng::from_seed(&seed[..]);
+    rng.shuffle(&mut cards);
+
+    let mut ids = (1..cards.len() + 1).collect::<Vec<usize>>();
+    rng.shuffle(&mut ids);
+    for (card, id) in cards.iter_mut().zip(ids) {
+        card.id = id;
+    }
     cards
 }
 
-#[derive(RustcEncodable)]
-pub struct CardKnowledge {
-    pub knows_color:      bool,
-    pub knows_number:     bool,
-    pub knows_color_not:  HashSet<Color>,
-    pub knows_number_not: HashSet<Number>,
+protocol_struct! {
+    /// A clue as received by one card of the hinted hand. Exactly one of `color` and `number` is set,
+    /// `touched` tells whether the clue applied to this card or excluded the value for it.
+    #[derive(RustcEncodable, Clone, PartialEq)]
+    pub struct ClueRecord {
+        pub giver:   Seat,
+        pub color:   Option<Color>,
+        pub number:  Option<Number>,
+        pub touched: bool,
+        pub turn:    usize,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable, Clone, PartialEq)]
+    pub struct CardKnowledge {
+        pub knows_color:      bool,
+        pub knows_number:     bool,
+        pub color:            Option<Color>,
+        pub number:           Option<Number>,
+        pub knows_color_not:  HashSet<Color>,
+        pub knows_number_not: HashSet<Number>,
+        pub clues:            Vec<ClueRecord>,
+    }
 }
 
 impl CardKnowledge {
@@ -82,16 +118,21 @@ impl CardKnowledge {
         CardKnowledge {
             knows_color:      false,
             knows_number:     false,
+            color:            None,
+            number:           None,
             knows_color_not:  HashSet::new(),
             knows_number_not: HashSet::new(),
+            clues:            Vec::new(),
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct CardInHand {
-    pub card:      Card,
-    pub knowledge: CardKnowledge,
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct CardInHand {
+        pub card:      Card,
+        pub knowledge: CardKnowledge,
+    }
 }
 
 impl CardInHand {
@@ -0,0 +1,56 @@
+use std::env;
+use std::path::PathBuf;
+
+const DATA_DIR_VAR:      &'static str = "HANABI_DATA_DIR";
+const ACCOUNTS_FILE_VAR: &'static str = "HANABI_ACCOUNTS_FILE";
+const REQUIRE_LOGIN_VAR: &'static str = "HANABI_REQUIRE_LOGIN";
+const TCP_ADDR_VAR:      &'static str = "HANABI_TCP_ADDR";
+const TLS_CERT_VAR:      &'static str = "HANABI_TLS_CERT";
+const TLS_KEY_VAR:       &'static str = "HANABI_TLS_KEY";
+const HTTP_ADDR_VAR:     &'static str = "HANABI_HTTP_ADDR";
+const LOG_FORMAT_VAR:    &'static str = "HANABI_LOG_FORMAT";
+const ADMIN_TOKEN_VAR:   &'static str = "HANABI_ADMIN_TOKEN";
+const DRAIN_SECS_VAR:    &'static str = "HANABI_DRAIN_SECS";
+const RANDOM_SEATS_VAR:  &'static str = "HANABI_RANDOM_SEATS";
+
+const DEFAULT_DRAIN_SECS: u64 = 60;
+
+pub struct Config {
+    pub data_dir:      Option<PathBuf>,
+    pub accounts_file: Option<PathBuf>,
+    pub require_login: bool,
+    pub tcp_addr:      Option<String>,
+    pub tls_cert:      Option<PathBuf>,
+    pub tls_key:       Option<PathBuf>,
+    pub http_addr:     Option<String>,
+    pub json_logs:     bool,
+    pub admin_token:   Option<String>,
+    pub drain_secs:    u64,
+    pub random_seats:  bool,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        debug!("Reading server configuration from environment.");
+        Config {
+            data_dir:      env::var_os(DATA_DIR_VAR).map(PathBuf::from),
+            accounts_file: env::var_os(ACCOUNTS_FILE_VAR).map(PathBuf::from),
+            require_login: flag(REQUIRE_LOGIN_VAR),
+            tcp_addr:      env::var(TCP_ADDR_VAR).ok(),
+            tls_cert:      env::var_os(TLS_CERT_VAR).map(PathBuf::from),
+            tls_key:       env::var_os(TLS_KEY_VAR).map(PathBuf::from),
+            http_addr:     env::var(HTTP_ADDR_VAR).ok(),
+            json_logs:     env::var(LOG_FORMAT_VAR).map(|f| f.eq_ignore_ascii_case("json")).unwrap_or(false),
+            admin_token:   env::var(ADMIN_TOKEN_VAR).ok().and_then(|t| if t.is_empty() { None } else { Some(t) }),
+            drain_secs:    env::var(DRAIN_SECS_VAR).ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_DRAIN_SECS),
+            random_seats:  flag(RANDOM_SEATS_VAR),
+        }
+    }
+}
+
+fn flag(var: &str) -> bool {
+    match env::var(var) {
+        Ok(val) => val == "1" || val.eq_ignore_ascii_case("true"),
+        Err(_)  => false,
+    }
+}
@@ -1,58 +1,85 @@
-use ws::{Handler, Message, Sender, Handshake, Result, CloseCode, Error};
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::error::Error as StdError;
-use server::Server;
-use responses::error_messages::MSG_TO_TXT_ERROR;
+use ws::{Sender, Result, CloseCode};
+use rustc_serialize::json::Json;
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use wire::WireFormat;
 use game_state::Void;
 
-pub struct Connection {
-    pub id:  u8,
-    pub out: Sender,
-    server:  Rc<RefCell<Server>>
-}
-
-impl Handler for Connection {
-    fn on_open(&mut self, _: Handshake) -> Result<Void> {
-        info!("On Open.");
-        Ok(())
-    }
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
 
-    fn on_close(&mut self, code: CloseCode, reason: &str) {
-        info!("On Close with CloseCode '{:?}' and Reason '{}'.", code ,reason);
-    }
+/// The sending half of a client's transport, which the server keeps to reach every client.
+#[derive(Clone)]
+pub enum Peer {
+    Websocket {
+        out:    Sender,
+        format: WireFormat,
+    },
+    /// Newline-delimited json over a plain TCP socket. The lines are written by a thread of their
+    /// own, so a client that stops reading never blocks the server.
+    Tcp {
+        lines:  SyncSender<String>,
+        stream: Arc<TcpStream>,
+    },
+}
 
-    fn on_error(&mut self, err: Error) {
-        error!("On Error with Error '{}'.", err);
+impl Peer {
+    pub fn send(&self, msg: &Json) -> Result<Void> {
+        match *self {
+            Peer::Websocket { ref out, format } => out.send(format.encode(msg)),
+            Peer::Tcp { ref lines, ref stream } => {
+                match lines.try_send(format!("{}\n", msg)) {
+                    Ok(())                             => {}
+                    Err(TrySendError::Full(_))         => {
+                        warn!("TCP client is not reading its messages and is disconnected.");
+                        stream.shutdown(Shutdown::Both).unwrap_or(());
+                    }
+                    // the client is already gone and about to be removed
+                    Err(TrySendError::Disconnected(_)) => {}
+                }
+                Ok(())
+            }
+        }
     }
 
-    fn on_message(&mut self, msg: Message) -> Result<Void> {
-        info!("On message.");
-        match msg.as_text() {
-            Ok(txt) => self.dispatch_req_to_server(&txt),
-            Err(e)  => self.dispatch_err_to_server(MSG_TO_TXT_ERROR, Some(e.description())),
+    /// Plain TCP has no close handshake, so the code and reason only reach websocket clients.
+    /// Shutting down a socket the client has already reset fails, which leaves nothing to close.
+    pub fn close(&self, code: CloseCode, reason: &str) -> Result<Void> {
+        match *self {
+            Peer::Websocket { ref out, .. } => out.close_with_reason(code, reason.to_owned()),
+            Peer::Tcp { ref stream, .. }    => {
+                stream.shutdown(Shutdown::Both).unwrap_or(());
+                Ok(())
+            }
         }
     }
 }
 
-impl Connection {
+/// A client as seen by the server, regardless of the transport it is connected through.
+pub struct Connection {
+    pub id: usize,
+    peer:   Peer,
+}
 
-    pub fn new(id: u8, out: Sender, server: Rc<RefCell<Server>>) -> Self {
-        debug!("Creating new Connection instance with id {}", id);
+impl Connection {
+    pub fn new(id: usize, peer: Peer) -> Self {
         Connection {
-            id:     id,
-            out:    out,
-            server: server,
+            id:   id,
+            peer: peer,
         }
     }
 
-    fn dispatch_err_to_server(&self, explanation: &'static str, details: Option<&str>) -> Result<Void> {
-        debug!("Dispatch err to server: \"{}\"", explanation);
-        self.server.borrow().answer_with_error_msg(&explanation, details, &self)
+    /// Ids are shared between all transports, so they stay unique on the server.
+    pub fn next_id() -> usize {
+        NEXT_ID.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub fn peer(&self) -> Peer {
+        self.peer.clone()
     }
 
-    fn dispatch_req_to_server(&self, req: &str) -> Result<Void> {
-        debug!("Dispatch Request to server.");
-        self.server.borrow_mut().handle_req(&req, &self)
+    pub fn send(&self, msg: &Json) -> Result<Void> {
+        self.peer.send(msg)
     }
 }
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use cards::{Card, CardKnowledge};
+use game_state::{GamePhase, GameState, Seat};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Place {
+    Deck,
+    Hand,
+    Played,
+    Discarded,
+}
+
+protocol_struct! {
+    /// Cards in a hand also carry their slot, so a card that only shifted within its hand counts as moved.
+    #[derive(RustcEncodable, Clone, PartialEq)]
+    pub struct CardLocation {
+        pub place:  Place,
+        pub player: Option<Seat>,
+        pub slot:   Option<usize>,
+    }
+}
+
+impl CardLocation {
+    fn new(place: Place) -> Self {
+        CardLocation {
+            place:  place,
+            player: None,
+            slot:   None,
+        }
+    }
+
+    fn hand(player: Seat, slot: usize) -> Self {
+        CardLocation {
+            place:  Place::Hand,
+            player: Some(player),
+            slot:   Some(slot),
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct CardMove {
+        pub card: Card,
+        pub from: CardLocation,
+        pub to:   CardLocation,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct TouchedCard {
+        pub player:    Seat,
+        pub card_id:   usize,
+        pub knowledge: CardKnowledge,
+    }
+}
+
+/// Where every card that is still in the game is located and what its holder knows about it.
+/// Played cards are not tracked individually, so a card that vanishes between two snapshots has been played.
+pub struct Snapshot {
+    cards: BTreeMap<usize, (Card, CardLocation, Option<CardKnowledge>)>,
+}
+
+impl Snapshot {
+    pub fn of(game_state: &GameState) -> Self {
+        let mut cards = BTreeMap::new();
+        for card in game_state.deck() {
+            cards.insert(card.id, (*card, CardLocation::new(Place::Deck), None));
+        }
+        for card in game_state.discarded_cards() {
+            cards.insert(card.id, (*card, CardLocation::new(Place::Discarded), None));
+        }
+        for player in game_state.players() {
+            for (slot, cih) in player.cards.iter().enumerate() {
+                cards.insert(cih.card.id, (cih.card, CardLocation::hand(player.seat, slot), Some(cih.knowledge.clone())));
+            }
+        }
+        Snapshot {
+            cards: cards,
+        }
+    }
+}
+
+protocol_struct! {
+    /// Everything that changed in the game state through a single action.
+    #[derive(RustcEncodable)]
+    pub struct StateDelta {
+        pub phase:         GamePhase,
+        pub hint_tokens:   usize,
+        pub err_tokens:    usize,
+        pub deck_size:     usize,
+        pub next_player:   Option<Seat>,
+        pub turns_left:    Option<usize>,
+        pub turn:          usize,
+        pub moved_cards:   Vec<CardMove>,
+        pub touched_cards: Vec<TouchedCard>,
+    }
+}
+
+impl StateDelta {
+    pub fn between(before: &Snapshot, game_state: &GameState) -> Self {
+        let after = Snapshot::of(game_state);
+        let mut moved_cards = Vec::new();
+        let mut touched_cards = Vec::new();
+
+        for (id, &(card, ref from, ref knowledge_before)) in &before.cards {
+            match after.cards.get(id) {
+                Some(&(_, ref to, _)) if to != from => moved_cards.push(CardMove {
+                    card: card,
+                    from: from.clone(),
+                    to:   to.clone(),
+                }),
+                Some(&(_, ref to, ref knowledge_after)) => {
+                    if let (Some(player), Some(knowledge)) = (to.player, knowledge_after.as_ref()) {
+                        if knowledge_before.as_ref() != Some(knowledge) {
+                            touched_cards.push(TouchedCard {
+                                player:    player,
+                                card_id:   *id,
+                                knowledge: knowledge.clone(),
+                            });
+                        }
+                    }
+                }
+                None => moved_cards.push(CardMove {
+                    card: card,
+                    from: from.clone(),
+                    to:   CardLocation::new(Place::Played),
+                }),
+            }
+        }
+
+        StateDelta {
+            phase:         game_state.phase(),
+            hint_tokens:   game_state.hint_tokens(),
+            err_tokens:    game_state.err_tokens(),
+            deck_size:     game_state.deck().len(),
+            next_player:   game_state.next_player(),
+            turns_left:    game_state.turns_left(),
+            turn:          game_state.turn(),
+            moved_cards:   moved_cards,
+            touched_cards: touched_cards,
+        }
+    }
+}
+
+/// The game state as it is attached to a response: either in full or only the changes.
+#[derive(Clone, Copy)]
+pub enum StateUpdate<'s> {
+    Full(&'s GameState),
+    Delta(&'s StateDelta),
+}
+
+impl<'s> StateUpdate<'s> {
+    pub fn full(&self) -> Option<&'s GameState> {
+        match *self {
+            StateUpdate::Full(game_state) => Some(game_state),
+            StateUpdate::Delta(_)         => None,
+        }
+    }
+
+    pub fn delta(&self) -> Option<&'s StateDelta> {
+        match *self {
+            StateUpdate::Full(_)      => None,
+            StateUpdate::Delta(delta) => Some(delta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_state::{Action, GameState, Seat};
+    use super::*;
+
+    fn hand(game_state: &GameState, player: Seat) -> Vec<usize> {
+        game_state.players()[player].cards.iter().map(|cih| cih.card.id).collect()
+    }
+
+    #[test]
+    fn deltas_keep_hands_in_order() {
+        let mut game_state = GameState::default();
+        game_state.add_player("alice").unwrap();
+        game_state.add_player("bob").unwrap();
+        game_state.start().unwrap();
+        let mut slots: Vec<Option<usize>> = hand(&game_state, 0).into_iter().map(Some).collect();
+        let discarded = slots[2].unwrap();
+
+        let before = Snapshot::of(&game_state);
+        game_state.apply(&Action::Discard { player: 0, card_id: discarded }).unwrap();
+        let delta = StateDelta::between(&before, &game_state);
+
+        // a client only seeing the delta rebuilds the hand from the slots it names
+        for card_move in delta.moved_cards.iter().filter(|m| m.from.player == Some(0)) {
+            slots[card_move.from.slot.unwrap()] = None;
+        }
+        for card_move in delta.moved_cards.iter().filter(|m| m.to.player == Some(0)) {
+            slots[card_move.to.slot.unwrap()] = Some(card_move.card.id);
+        }
+        assert!(!slots.contains(&Some(discarded)));
+        assert_eq!(slots.into_iter().map(Option::unwrap).collect::<Vec<_>>(), hand(&game_state, 0));
+    }
+}
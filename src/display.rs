@@ -1,7 +1,8 @@
 use std::fmt::{Display, Formatter, Result};
 use cards::{Card, Color, Number, CardKnowledge};
 use responses::ResponseType;
-use game_state::Player;
+use game_state::{Action, Player};
+use msgpack::DecodeError;
 
 impl Display for Color {
     fn fmt(&self, f: &mut Formatter) -> Result {
@@ -30,14 +31,24 @@ impl Display for Number {
 impl Display for ResponseType {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match *self {
-            ResponseType::ConnectionResponseType  => write!(f, "Connection Response Type"),
-            ResponseType::DiscardCardResponseType => write!(f, "Discard Card Response Type"),
-            ResponseType::ErrorResponseType       => write!(f, "Error Response Type"),
-            ResponseType::GameOverResponseType    => write!(f, "Game Over Response Type"),
-            ResponseType::HintColorResposeType    => write!(f, "Hint Color Respose Type"),
-            ResponseType::HintNumberResposeType   => write!(f, "Hint Number Respose Type"),
-            ResponseType::PlayCardResponseType    => write!(f, "Play Card Response Type"),
-            ResponseType::GameStartResponseType   => write!(f, "Game Start Response Type"),
+            ResponseType::ConnectionResponseType   => write!(f, "Connection Response Type"),
+            ResponseType::DiscardCardResponseType  => write!(f, "Discard Card Response Type"),
+            ResponseType::ErrorResponseType        => write!(f, "Error Response Type"),
+            ResponseType::GameOverResponseType     => write!(f, "Game Over Response Type"),
+            ResponseType::HintColorResposeType     => write!(f, "Hint Color Respose Type"),
+            ResponseType::HintNumberResposeType    => write!(f, "Hint Number Respose Type"),
+            ResponseType::PlayCardResponseType     => write!(f, "Play Card Response Type"),
+            ResponseType::GameStartResponseType    => write!(f, "Game Start Response Type"),
+            ResponseType::LoginResponseType        => write!(f, "Login Response Type"),
+            ResponseType::StatsResponseType        => write!(f, "Stats Response Type"),
+            ResponseType::HelloResponseType        => write!(f, "Hello Response Type"),
+            ResponseType::SyncResponseType         => write!(f, "Sync Response Type"),
+            ResponseType::AdminResponseType        => write!(f, "Admin Response Type"),
+            ResponseType::NoticeResponseType       => write!(f, "Notice Response Type"),
+            ResponseType::LegalActionsResponseType => write!(f, "Legal Actions Response Type"),
+            ResponseType::SeatOrderResponseType    => write!(f, "Seat Order Response Type"),
+            ResponseType::MoveCardResponseType     => write!(f, "Move Card Response Type"),
+            ResponseType::NoteResponseType         => write!(f, "Note Response Type"),
         }
     }
 }
@@ -50,7 +61,7 @@ impl Display for Card {
 
 impl Display for Player {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "Player {}:", self.name).unwrap();
+        write!(f, "Player {} on seat {}:", self.name, self.seat).unwrap();
         for cih in &self.cards {
             write!(f, "\nCard {} with Knowledge {}", cih.card, cih.knowledge).unwrap();
         }
@@ -58,12 +69,37 @@ impl Display for Player {
     }
 }
 
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match *self {
+            Action::Discard    { player, card_id }        => write!(f, "Seat {} discards card #{}", player, card_id),
+            Action::Play       { player, card_id }        => write!(f, "Seat {} plays card #{}", player, card_id),
+            Action::HintColor  { player, target, color }  => write!(f, "Seat {} hints {} to seat {}", player, color, target),
+            Action::HintNumber { player, target, number } => write!(f, "Seat {} hints {} to seat {}", player, number, target),
+        }
+    }
+}
+
 impl Display for CardKnowledge {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "Knows Color: {}, Knows Number: {}, Excluded Colors: {:?}, Excluded Numbers: {:?}",
-               self.knows_color,
-               self.knows_number,
+        write!(f, "Knows Color: {}, Knows Number: {}, Excluded Colors: {:?}, Excluded Numbers: {:?}, Clues: {}",
+               self.color.map_or("-".to_owned(), |c| format!("{}", c)),
+               self.number.map_or("-".to_owned(), |n| format!("{}", n)),
                self.knows_color_not.iter().map(|c| format!("{}", c)).collect::<Vec<_>>(),
-               self.knows_number_not.iter().map(|n| format!("{}", n)).collect::<Vec<_>>())
+               self.knows_number_not.iter().map(|n| format!("{}", n)).collect::<Vec<_>>(),
+               self.clues.len())
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match *self {
+            DecodeError::UnexpectedEnd         => write!(f, "Unexpected end of MessagePack data"),
+            DecodeError::UnsupportedType(byte) => write!(f, "Unsupported MessagePack type 0x{:02x}", byte),
+            DecodeError::InvalidUtf8           => write!(f, "MessagePack string is not valid UTF-8"),
+            DecodeError::NonStringKey          => write!(f, "MessagePack map key is not a string"),
+            DecodeError::TrailingBytes         => write!(f, "Trailing bytes after MessagePack value"),
+            DecodeError::TooDeep               => write!(f, "MessagePack arrays and maps are nested too deeply"),
+        }
     }
 }
@@ -1,7 +1,9 @@
 use cards;
-use cards::{Card, Color, Number, CardInHand};
+use cards::{Card, Color, Number, CardInHand, ClueRecord, Seed};
 use responses::error_messages::*;
+use storage::GameRecord;
 
+use rand::{ChaChaRng, Rng, SeedableRng};
 use std::collections::HashMap;
 use std::mem;
 use std::default::Default;
@@ -12,33 +14,107 @@ const CARDS_IN_DECK:        usize = 50;
 const DEFAULT_HINT_TOKENS:  usize = 8;
 const DEFAULT_ERR_TOKENS:   usize = 3;
 const FOUR_CARDS_THRESHOLD: usize = 4;
-
-#[derive(RustcEncodable)]
-pub struct Player {
-    pub name:  String,
-    pub cards: Vec<CardInHand>,
+const STANDARD_VARIANT:     &'static str = "STANDARD";
+/// Keeps the random sequence the seats are shuffled with apart from the deck's, which uses nonce `0`.
+const SEATS_NONCE:          u64   = 1;
+
+const ALL_COLORS:  [Color; 5]  = [Color::Red, Color::Yellow, Color::Green, Color::Blue, Color::White];
+const ALL_NUMBERS: [Number; 5] = [Number::One, Number::Two, Number::Three, Number::Four, Number::Five];
+
+/// Where a player sits at the table, counted from `0`. Seats follow the order the players joined
+/// unless the host arranges them before the game starts, after that they never change. Players
+/// are addressed by their seat, their name is only for display.
+pub type Seat = usize;
+
+/// Hands are ordered as in physical play: slot `0` is the leftmost card and every drawn card is
+/// put there, so the cards to its right shift one slot further.
+pub const DRAW_SLOT: usize = 0;
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct Player {
+        pub seat:  Seat,
+        pub name:  String,
+        pub cards: Vec<CardInHand>,
+    }
 }
 
 impl Player {
-    pub fn new(name: String, cards: Vec<CardInHand>) -> Self {
+    pub fn new(seat: Seat, name: String, cards: Vec<CardInHand>) -> Self {
         Player {
+            seat:  seat,
             name:  name,
             cards: cards,
         }
     }
 }
 
-#[derive(RustcEncodable)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FinishReason {
+    /// A card was misplayed when no error tokens were left.
+    Strikes,
+    /// Every player had their last turn after the deck ran out.
+    FinalRoundOver,
+    /// The game was ended from outside, e.g. by an administrator.
+    Aborted,
+}
+
+/// The phases of a game, which only ever move forward:
+/// `Lobby -> Dealing -> InProgress -> FinalRound -> Finished`, and any phase may end in `Finished`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GamePhase {
+    /// Players join, nobody holds cards yet.
+    Lobby,
+    /// The hands are dealt, when this fails the game returns to the lobby.
+    Dealing,
+    InProgress,
+    /// The deck is empty and the players in `final_turns` still have their last turn.
+    FinalRound,
+    Finished { reason: FinishReason },
+}
+
+impl GamePhase {
+    fn can_become(&self, next: &GamePhase) -> bool {
+        match (*self, *next) {
+            (GamePhase::Lobby,      GamePhase::Dealing)          => true,
+            (GamePhase::Dealing,    GamePhase::InProgress)       => true,
+            (GamePhase::Dealing,    GamePhase::Lobby)            => true,
+            (GamePhase::InProgress, GamePhase::FinalRound)       => true,
+            (GamePhase::Finished { .. }, _)                      => false,
+            (_,                     GamePhase::Finished { .. })  => true,
+            _                                                    => false,
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        *self != GamePhase::Lobby && *self != GamePhase::Dealing
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match *self {
+            GamePhase::Finished { .. } => true,
+            _                          => false,
+        }
+    }
+}
+
+/// Encoded by hand, as the seed of the deck must not reach the clients.
 pub struct GameState {
+    phase:           GamePhase,
     hint_tokens:     usize,
     hint_tokens_max: usize,
     err_tokens:      usize,
     played_cards:    HashMap<Color, Number>,
     players:         Vec<Player>,
+    host:            Option<Seat>,
+    first_player:    Seat,
+    seed:            Seed,
     deck:            Vec<Card>,
     discarded_cards: Vec<Card>,
-    next_player:     String,
+    next_player:     Option<Seat>,
     turns_left:      Option<usize>,
+    final_turns:     Vec<Seat>,
+    turn:            usize,
 }
 
 impl Default for GameState {
@@ -51,87 +127,282 @@ impl Default for GameState {
 impl GameState {
 
     pub fn new(hint_tokens_max: usize, err_tokens: usize) -> Self {
+        let seed = cards::new_seed();
+        GameState::with_deck(hint_tokens_max, err_tokens, seed, cards::new_deck(&seed))
+    }
+
+    /// The seed has to be the one the deck was shuffled with.
+    pub fn with_deck(hint_tokens_max: usize, err_tokens: usize, seed: Seed, deck: Vec<Card>) -> Self {
         debug!("Creating new game state instace.");
         GameState {
+            phase:           GamePhase::Lobby,
             hint_tokens:     hint_tokens_max,
             hint_tokens_max: hint_tokens_max,
             err_tokens:      err_tokens,
             played_cards:    HashMap::new(),
             players:         Vec::with_capacity(6),
-            deck:            cards::new_deck(),
+            host:            None,
+            first_player:    0,
+            seed:            seed,
+            deck:            deck,
             discarded_cards: Vec::with_capacity(CARDS_IN_DECK),
-            next_player:     String::new(),
+            next_player:     None,
             turns_left:      None,
+            final_turns:     Vec::new(),
+            turn:            0,
         }
     }
 
     pub fn add_player(&mut self, name: &str) -> Result<Void, &'static str> {
         info!("Adding new player {}.", name);
 
+        if self.phase != GamePhase::Lobby {
+            error!("Players cannot join in phase {:?}.", self.phase);
+            return Err(CONN_GAME_ALREADY_STARTED);
+        }
+
         if self.players.iter().any(|p| p.name == name) {
             error!("Player already exists.");
             return Err(PLAYER_ALREADY_EXISTS);
         }
 
         let no_of_players = self.players.len() + 1;
-        let cards_per_player = match no_of_players >= FOUR_CARDS_THRESHOLD {
-            false => {
-                debug!("Number of players below threshold. New player will receive 5 cards.");
-                5
-            }
-            true  => {
-                if no_of_players == FOUR_CARDS_THRESHOLD {
-                    debug!("Threshold for number of players crossed. Removing cards from all previously added players.");
-                    for player in &mut self.players {
-                        self.deck.push(player.cards.pop().unwrap().card);
-                    }
-                }
-                debug!("Number of players above threshold. New player will receive 4 cards.");
-                4
-            }
-        };
-
-        if self.deck.len() < cards_per_player {
+        if self.deck.len() < no_of_players * cards_per_player(no_of_players) {
             error!("Not enough cards for new player.");
             return Err(NO_CARDS);
         }
 
+        let seat = self.players.len();
+        self.players.push(Player::new(seat, name.into(), Vec::new()));
+        self.host = self.host.or(Some(seat));
+        debug!("Number of players increased to {}", self.players.len());
 
-        let cards = self.deck
-            .drain(0..cards_per_player)
-            .map(|c| CardInHand::new(c))
-            .collect();
+        Ok(())
+    }
 
-        self.players.push(Player::new(name.into(), cards));
-        self.next_player = name.into();
+    /// Rearranges the seats in the lobby: `order` lists the current seats in their new order and
+    /// `first_player` is the new seat of the player who is going to take the first turn.
+    pub fn arrange(&mut self, order: &[Seat], first_player: Seat) -> Result<Void, &'static str> {
+        if self.phase != GamePhase::Lobby {
+            error!("Seats cannot be arranged in phase {:?}.", self.phase);
+            return Err(GAME_ALREADY_STARTED);
+        }
+        let mut seen = vec![false; self.players.len()];
+        let is_permutation = order.len() == seen.len() && order.iter().all(|&s| s < seen.len() && !mem::replace(&mut seen[s], true));
+        if !is_permutation || first_player >= order.len() {
+            error!("Seat order {:?} with first player {} is invalid.", order, first_player);
+            return Err(INVALID_SEAT_ORDER);
+        }
+        let mut players = self.players.drain(..).map(Some).collect::<Vec<_>>();
+        self.players = order.iter().map(|&s| players[s].take().unwrap()).collect();
+        for (seat, player) in self.players.iter_mut().enumerate() {
+            player.seat = seat;
+        }
+        self.host = self.host.and_then(|host| order.iter().position(|&s| s == host));
+        self.first_player = first_player;
+        debug!("Seats arranged as {:?}, seat {} starts.", self.player_names(), first_player);
+        Ok(())
+    }
 
-        debug!("Added new player: {}", self.players[self.players.len()-1]);
-        debug!("Number of players increased to {}", self.players.len());
+    /// Randomizes the seat order and the starting player, derived from the seed of the deck like
+    /// the card ids. The same seed and players always result in the same seating.
+    pub fn shuffle_seats(&mut self) -> Result<Void, &'static str> {
+        if self.players.is_empty() {
+            return Ok(());
+        }
+        let mut rng = ChaChaRng::from_seed(&self.seed[..]);
+        rng.set_counter(0, SEATS_NONCE);
+        let mut order = (0..self.players.len()).collect::<Vec<Seat>>();
+        rng.shuffle(&mut order);
+        let first_player = rng.gen_range(0, order.len());
+        self.arrange(&order, first_player)
+    }
+
+    /// Deals the hands and hands the first turn to the starting player, who is the player who
+    /// joined first unless the seats were arranged.
+    pub fn start(&mut self) -> Result<Void, &'static str> {
+        try!(self.check_start());
+        try!(self.transition(GamePhase::Dealing));
+        let cards_per_player = cards_per_player(self.players.len());
+        debug!("Dealing {} cards to each of {} players.", cards_per_player, self.players.len());
+        for player in &mut self.players {
+            player.cards = self.deck.drain(0..cards_per_player).rev().map(|c| CardInHand::new(c)).collect();
+        }
+        self.next_player = Some(self.first_player);
+        self.transition(GamePhase::InProgress)
+    }
+
+    /// Checks whether the game could be started right now, without changing anything.
+    pub fn check_start(&self) -> Result<Void, &'static str> {
+        try!(self.check_transition(&GamePhase::Dealing));
+        if self.players.is_empty() || self.deck.len() < self.players.len() * cards_per_player(self.players.len()) {
+            error!("Not enough cards to deal {} players.", self.players.len());
+            return Err(NO_CARDS);
+        }
+        Ok(())
+    }
 
+    /// Ends the game before it is played out.
+    pub fn abort(&mut self) -> Result<Void, &'static str> {
+        self.transition(GamePhase::Finished { reason: FinishReason::Aborted })
+    }
+
+    fn transition(&mut self, next: GamePhase) -> Result<Void, &'static str> {
+        try!(self.check_transition(&next));
+        debug!("Game moves from phase {:?} to {:?}.", self.phase, next);
+        self.phase = next;
         Ok(())
     }
 
-    pub fn discard_card(&mut self, name: &str, discarded_card_id: usize) -> DiscardCardResult {
-        info!("Discarding card #{} of player {}.", discarded_card_id, name);
+    fn check_transition(&self, next: &GamePhase) -> Result<Void, &'static str> {
+        if !self.phase.can_become(next) {
+            error!("Game cannot move from phase {:?} to {:?}.", self.phase, next);
+            return Err(match self.phase {
+                GamePhase::Finished { .. }              => GAME_IS_OVER,
+                GamePhase::Lobby | GamePhase::Dealing   => GAME_NOT_STARTED,
+                _                                       => GAME_ALREADY_STARTED,
+            });
+        }
+        Ok(())
+    }
 
-        if let Some(p_index) = self.player_index(name) {
-            if let Some(c_index) = self.card_index(p_index, discarded_card_id) {
-                return self.do_discard_card(p_index, c_index)
-            } else {
-                error!("Could not find card #{} on the hand of player {}", discarded_card_id, name);
-                return DiscardCardResult::Err(CARD_NOT_FOUND)
+    /// Checks whether `action` could be applied right now, without changing anything.
+    pub fn validate(&self, action: &Action) -> Result<Void, RuleError> {
+        try!(self.check_playing());
+        let seat = action.player();
+        if seat >= self.players.len() {
+            return Err(RuleError::PlayerNotFound);
+        }
+        if Some(seat) != self.next_player {
+            return Err(RuleError::NotYourTurn);
+        }
+        match *action {
+            Action::Discard { card_id, .. } | Action::Play { card_id, .. } => {
+                try!(self.card_index(seat, card_id).ok_or(RuleError::CardNotFound));
+            }
+            Action::HintColor { target, .. } | Action::HintNumber { target, .. } => {
+                if target >= self.players.len() {
+                    return Err(RuleError::PlayerNotFound);
+                }
+                if target == seat {
+                    return Err(RuleError::HintToSelf);
+                }
+                if self.hint_tokens == 0 {
+                    return Err(RuleError::NoHintTokens);
+                }
             }
-        } else {
-            error!("Could not find player {}", name);
-            return DiscardCardResult::Err(PLAYER_NOT_FOUND)
         }
+        Ok(())
     }
 
-    fn do_discard_card(&mut self, p_index: usize, c_index: usize) -> DiscardCardResult {
-        match self.maybe_turn_has_passed() {
-            Err(msg) => return DiscardCardResult::Err(msg),
-            _ => {}
+    pub fn apply(&mut self, action: &Action) -> Result<Outcome, RuleError> {
+        info!("Applying action {}.", action);
+        if let Err(e) = self.validate(action) {
+            error!("Action {} is not allowed: {}", action, e.explanation());
+            return Err(e);
+        }
+        let p_index = action.player();
+        Ok(match *action {
+            Action::Discard { card_id, .. } => {
+                let c_index = self.card_index(p_index, card_id).unwrap();
+                self.do_discard_card(p_index, c_index)
+            }
+            Action::Play { card_id, .. } => {
+                let c_index = self.card_index(p_index, card_id).unwrap();
+                self.do_play_card(p_index, c_index)
+            }
+            Action::HintColor { player, target, color } => {
+                let clue = ClueRecord { giver: player, color: Some(color), number: None, touched: false, turn: 0 };
+                self.knowledge_update(target,
+                                      clue,
+                                      &|c| { c.card.color == color },
+                                      &|c| { c.knowledge.knows_color = true; c.knowledge.color = Some(color); c.knowledge.knows_color_not.clear(); },
+                                      &|c| { c.knowledge.knows_color_not.insert(color); })
+            }
+            Action::HintNumber { player, target, number } => {
+                let clue = ClueRecord { giver: player, color: None, number: Some(number), touched: false, turn: 0 };
+                self.knowledge_update(target,
+                                      clue,
+                                      &|c| { c.card.number == number },
+                                      &|c| { c.knowledge.knows_number = true; c.knowledge.number = Some(number); c.knowledge.knows_number_not.clear(); },
+                                      &|c| { c.knowledge.knows_number_not.insert(number); })
+            }
+        })
+    }
+
+    /// Every action `player` could take right now, i.e. every candidate that passes `validate`.
+    /// Hints may be negative, so every color and number can be hinted to every other player.
+    pub fn legal_actions(&self, player: Seat) -> Vec<Action> {
+        let mut candidates = Vec::new();
+        if let Some(p) = self.players.get(player) {
+            for cih in &p.cards {
+                candidates.push(Action::Play { player: player, card_id: cih.card.id });
+                candidates.push(Action::Discard { player: player, card_id: cih.card.id });
+            }
+        }
+        for target in 0..self.players.len() {
+            for color in &ALL_COLORS {
+                candidates.push(Action::HintColor { player: player, target: target, color: *color });
+            }
+            for number in &ALL_NUMBERS {
+                candidates.push(Action::HintNumber { player: player, target: target, number: *number });
+            }
+        }
+        candidates.into_iter().filter(|a| self.validate(a).is_ok()).collect()
+    }
+
+    /// The ids of the cards a hint would touch, in hand order. Empty for plays and discards.
+    pub fn touched_cards(&self, action: &Action) -> Vec<usize> {
+        let (target, touches): (Seat, Box<Fn(&Card) -> bool>) = match *action {
+            Action::HintColor { target, color, .. }   => (target, Box::new(move |c| c.color == color)),
+            Action::HintNumber { target, number, .. } => (target, Box::new(move |c| c.number == number)),
+            _                                         => return Vec::new(),
         };
+        self.players.get(target).map_or(Vec::new(), |p| {
+            p.cards.iter().filter(|c| touches(&c.card)).map(|c| c.card.id).collect()
+        })
+    }
+
+    /// The position of a card in its holder's hand.
+    pub fn slot(&self, player: Seat, card_id: usize) -> Option<usize> {
+        self.players.get(player).and_then(|_| self.card_index(player, card_id))
+    }
+
+    /// Moves a card to another slot of its holder's hand, the cards in between shift by one slot.
+    /// Players may rearrange their hands at any time of the game, this does not take a turn.
+    /// Returns the slot the card was moved from.
+    pub fn move_card(&mut self, player: Seat, card_id: usize, to_slot: usize) -> Result<usize, RuleError> {
+        try!(self.check_playing());
+        if player >= self.players.len() {
+            return Err(RuleError::PlayerNotFound);
+        }
+        let from_slot = try!(self.card_index(player, card_id).ok_or(RuleError::CardNotFound));
+        let hand = &mut self.players[player].cards;
+        if to_slot >= hand.len() {
+            return Err(RuleError::InvalidSlot);
+        }
+        let card = hand.remove(from_slot);
+        hand.insert(to_slot, card);
+        debug!("Seat {} moved card #{} from slot {} to slot {}.", player, card_id, from_slot, to_slot);
+        Ok(from_slot)
+    }
+
+    /// Notes can be attached to any card in a hand while the game is running.
+    pub fn validate_note(&self, card_id: usize) -> Result<Void, RuleError> {
+        try!(self.check_playing());
+        if self.players.iter().any(|p| p.cards.iter().any(|c| c.card.id == card_id)) {
+            Ok(())
+        } else {
+            Err(RuleError::CardNotFound)
+        }
+    }
+
+    /// The id of the card at a position in a player's hand.
+    pub fn card_at(&self, player: Seat, slot: usize) -> Option<usize> {
+        self.players.get(player).and_then(|p| p.cards.get(slot)).map(|c| c.card.id)
+    }
+
+    fn do_discard_card(&mut self, p_index: usize, c_index: usize) -> Outcome {
         let (discarded_card, drawn_card) = self.maybe_draw_new_card(p_index, c_index);
         self.discarded_cards.push(discarded_card);
 
@@ -139,37 +410,22 @@ impl GameState {
             debug!("Card discarded - number of hint tokens increased to {}", self.hint_tokens);
             self.hint_tokens += 1;
         }
+        self.end_turn();
 
-        DiscardCardResult::Ok {
+        Outcome::Discarded {
+            slot:           c_index,
             discarded_card: discarded_card,
             drawn_card:     drawn_card
         }
     }
 
-    pub fn play_card(&mut self, name: &str, played_card_id: usize) -> CardPlayingResult {
-        debug!("Playing card #{} of player {}", played_card_id, name);
-
-        if let Some(p_index) = self.player_index(name) {
-            if let Some(c_index) = self.card_index(p_index, played_card_id) {
-                return self.do_play_card(p_index, c_index)
-            } else {
-                error!("Could not find card #{} on the hand of player {}", played_card_id, name);
-                return CardPlayingResult::Err(CARD_NOT_FOUND)
-            }
-        } else {
-            error!("Could not find player {}", name);
-            return CardPlayingResult::Err(PLAYER_NOT_FOUND)
-        }
-    }
-
-    fn do_play_card(&mut self, p_index: usize, c_index: usize) -> CardPlayingResult {
-        match self.maybe_turn_has_passed() {
-            Err(msg) => return CardPlayingResult::Err(msg),
-            _ => {}
-        };
+    /// A misplay without error tokens left ends the game with `FinishReason::Strikes`, even on the
+    /// last turn of the final round, so the play is resolved before the turn ends.
+    fn do_play_card(&mut self, p_index: usize, c_index: usize) -> Outcome {
         let (played_card, drawn_card) = self.maybe_draw_new_card(p_index, c_index);
 
-        if Number::is_next_largest(self.played_cards.get(&played_card.color), &played_card.number) {
+        let success = Number::is_next_largest(self.played_cards.get(&played_card.color), &played_card.number);
+        if success {
             debug!("Play card success. Currently played cards:\n {:?}",
                    self.played_cards.iter().map(|(color, number)| format!("{}: {}\n", color, number)).collect::<Vec<_>>());
 
@@ -179,136 +435,188 @@ impl GameState {
             }
 
             self.played_cards.insert(played_card.color, played_card.number);
-
-            CardPlayingResult::Ok {
-                success:     true,
-                played_card: played_card,
-                drawn_card:  drawn_card,
-            }
+        } else if self.err_tokens == 0 {
+            debug!("Play card fail and all error tokens are used up.");
+            self.finish(FinishReason::Strikes);
         } else {
-            match self.err_tokens {
-                0 => CardPlayingResult::EpicFail,
-                _ => {
-                    self.discarded_cards.push(played_card);
-                    self.err_tokens -= 1;
-                    debug!("Play card fail. {} err tokens left.", self.err_tokens);
-                    CardPlayingResult::Ok {
-                        success:     false,
-                        played_card: played_card,
-                        drawn_card:  drawn_card,
-                    }
-                }
-            }
+            self.discarded_cards.push(played_card);
+            self.err_tokens -= 1;
+            debug!("Play card fail. {} err tokens left.", self.err_tokens);
+        }
+        self.end_turn();
+
+        Outcome::Played {
+            slot:        c_index,
+            success:     success,
+            played_card: played_card,
+            drawn_card:  drawn_card,
         }
     }
 
+    /// The replacement goes to `DRAW_SLOT` rather than into the gap the removed card left.
     fn maybe_draw_new_card(&mut self, p_index: usize, c_index: usize) -> (Card, Option<Card>) {
-        self.set_next_player();
         let mut hand = &mut self.players[p_index].cards;
+        let removed_card = hand.remove(c_index).card;
         match self.deck.pop() {
             Some(new_card) => {
                 debug!("Card removed and {} drawn as replacement", new_card);
-                let mut new_card_in_hand = CardInHand::new(new_card);
-                mem::swap(&mut new_card_in_hand, &mut hand[c_index]);
-                (new_card_in_hand.card, Some(hand[c_index].card))
+                hand.insert(DRAW_SLOT, CardInHand::new(new_card));
+                (removed_card, Some(new_card))
             }
             None => {
                 debug!("Deck is empty and removed card will not be replaced.");
-                (hand.remove(c_index).card, None)
+                (removed_card, None)
             }
         }
     }
 
-    pub fn hint_color(&mut self, name: &str, color: &Color) -> Result<Void, &'static str> {
-        info!("Hinting color {} for player {}.", color, name);
-        self.knowledge_update(name,
-                              &|c| { c.card.color == *color },
-                              &|c| { c.knowledge.knows_color = true; c.knowledge.knows_color_not.clear(); },
-                              &|c| { c.knowledge.knows_color_not.insert(color.clone()); })
-    }
-
-    pub fn hint_number(&mut self, name: &str, number: &Number) -> Result<Void, &'static str> {
-        info!("Hinting number {} for player {}.", number, name);
-        self.knowledge_update(name,
-                              &|c| { c.card.number == *number },
-                              &|c| { c.knowledge.knows_number = true; c.knowledge.knows_number_not.clear(); },
-                              &|c| { c.knowledge.knows_number_not.insert(number.clone()); })
-    }
-
+    /// Every card of the hinted hand records the clue, whether it was touched by it or not.
     fn knowledge_update(&mut self,
-                        name: &str,
+                        p_index: Seat,
+                        mut clue: ClueRecord,
                         predicate: &Fn(&CardInHand) -> bool,
                         update_positive: &Fn(&mut CardInHand),
                         update_negative: &Fn(&mut CardInHand))
-                        -> Result<Void, &'static str>
+                        -> Outcome
     {
-        debug!("Update knowledge for player {}.", name);
-        try!(self.maybe_turn_has_passed());
-        if let Some(p_index) = self.player_index(&name) {
-            try!(self.use_hint());
-            self.set_next_player();
-
-            for mut card_in_hand in &mut self.players[p_index].cards {
-                match predicate(&card_in_hand) {
-                    true  => update_positive(&mut card_in_hand),
-                    false => update_negative(&mut card_in_hand),
-                }
+        debug!("Update knowledge for player {}.", self.players[p_index].name);
+        self.hint_tokens -= 1;
+        debug!("Hint used, {} tokens left.", self.hint_tokens);
+        clue.turn = self.turn + 1;
+
+        for mut card_in_hand in &mut self.players[p_index].cards {
+            clue.touched = predicate(&card_in_hand);
+            match clue.touched {
+                true  => update_positive(&mut card_in_hand),
+                false => update_negative(&mut card_in_hand),
             }
-            return Ok(())
-        } else {
-            error!("Could not find player {}", name);
-            return Err(PLAYER_NOT_FOUND)
+            card_in_hand.knowledge.clues.push(clue.clone());
         }
+        self.end_turn();
+        Outcome::Hinted
     }
 
-    pub fn get_next_player(&self) -> &str {
-        self.next_player.as_str()
+    pub fn record(&self) -> GameRecord {
+        GameRecord {
+            hint_tokens_max: self.hint_tokens_max,
+            err_tokens:      self.err_tokens,
+            seed:            Some(self.seed),
+            deck:            self.deck.clone(),
+        }
     }
 
-    fn set_next_player(&mut self) {
-        match self.next_player.as_str() {
-            "" => self.next_player = self.players[0].name.clone(),
-            _  => {
-                let index = (self.players.iter().position(|p| p.name == self.next_player).unwrap() + 1) % self.players.len();
-                self.next_player = self.players[index].name.clone();
-            }
-        }
-        debug!("Set next player to {:?}", self.next_player);
+    pub fn player_names(&self) -> Vec<&str> {
+        self.players.iter().map(|p| p.name.as_str()).collect()
     }
 
-    fn use_hint(&mut self) -> Result<Void, &'static str> {
-        if self.hint_tokens <= 0 {
-            error!("Cannot use hint without hint tokens.");
-            Err(NO_HINT_TOKENS)
-        } else {
-            self.hint_tokens -= 1;
-            debug!("Hint used, {} tokens left.", self.hint_tokens);
-            Ok(())
-        }
+    pub fn variant(&self) -> &'static str {
+        STANDARD_VARIANT
     }
 
-    fn maybe_turn_has_passed(&mut self) -> Result<Void, &'static str> {
-        if self.deck.is_empty() {
-            self.turns_left = match self.turns_left {
-                None => {
-                    debug!("Deck is empty, leaving every player with 1 more turn to go.");
-                    Some(self.players.len()-1)
-                }
-                Some(t) if t > 0 => {
-                    debug!("Deck is empty and {} more turns are left.", t-1);
-                    Some(t-1)
-                }
-                Some(_) => {
-                    error!("Tried to execute an action when the game was already over with 0 turns left.");
-                    return Err(GAME_IS_OVER)
+    pub fn phase(&self) -> GamePhase {
+        self.phase
+    }
+
+    pub fn has_player(&self, name: &str) -> bool {
+        self.seat_of(name).is_some()
+    }
+
+    /// Looks up a player's seat by name, names are unique within a game.
+    pub fn seat_of(&self, name: &str) -> Option<Seat> {
+        self.players.iter().position(|p| p.name == name)
+    }
+
+    pub fn name_of(&self, seat: Seat) -> &str {
+        &self.players[seat].name
+    }
+
+    pub fn hint_tokens(&self) -> usize {
+        self.hint_tokens
+    }
+
+    pub fn hint_tokens_max(&self) -> usize {
+        self.hint_tokens_max
+    }
+
+    pub fn played_cards(&self) -> &HashMap<Color, Number> {
+        &self.played_cards
+    }
+
+    pub fn err_tokens(&self) -> usize {
+        self.err_tokens
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn deck(&self) -> &[Card] {
+        &self.deck
+    }
+
+    pub fn discarded_cards(&self) -> &[Card] {
+        &self.discarded_cards
+    }
+
+    /// The seat of the player who joined first and may arrange the seats.
+    pub fn host(&self) -> Option<Seat> {
+        self.host
+    }
+
+    pub fn first_player(&self) -> Seat {
+        self.first_player
+    }
+
+    /// The seat whose turn it is, `None` before the game has started.
+    pub fn next_player(&self) -> Option<Seat> {
+        self.next_player
+    }
+
+    /// The number of turns played so far, the first turn of the game is turn 1.
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Called once by every action after it has been resolved. Once the deck has run out every
+    /// player, including the one who drew the last card, has exactly one more turn. A game the
+    /// action has already finished stays finished.
+    fn end_turn(&mut self) {
+        let acting_player = self.next_player.unwrap();
+        self.turn += 1;
+        self.next_player = Some((acting_player + 1) % self.players.len());
+        debug!("Set next player to {:?}", self.next_player);
+
+        match self.phase {
+            GamePhase::InProgress if self.deck.is_empty() => {
+                debug!("Deck is empty, leaving every player with 1 more turn to go.");
+                self.final_turns = (0..self.players.len()).collect();
+                self.turns_left = Some(self.final_turns.len());
+                self.transition(GamePhase::FinalRound).unwrap_or(());
+            }
+            GamePhase::FinalRound => {
+                self.final_turns.retain(|seat| *seat != acting_player);
+                self.turns_left = Some(self.final_turns.len());
+                if self.final_turns.is_empty() {
+                    self.finish(FinishReason::FinalRoundOver);
                 }
             }
-        };
-        Ok(())
+            _ => {}
+        }
     }
 
-    fn player_index(&self, name: &str) -> Option<usize> {
-        self.players.iter().position(|p| p.name == name)
+    /// Ends the game unless it is already over, in which case it keeps the reason it ended for first.
+    fn finish(&mut self, reason: FinishReason) {
+        if !self.phase.is_finished() {
+            self.transition(GamePhase::Finished { reason: reason }).unwrap_or(());
+        }
+    }
+
+    fn check_playing(&self) -> Result<Void, RuleError> {
+        match self.phase {
+            GamePhase::InProgress | GamePhase::FinalRound => Ok(()),
+            GamePhase::Lobby | GamePhase::Dealing         => Err(RuleError::GameNotStarted),
+            GamePhase::Finished { .. }                    => Err(RuleError::GameIsOver),
+        }
     }
 
     fn card_index(&self, p_index: usize, id: usize) -> Option<usize> {
@@ -319,6 +627,11 @@ impl GameState {
         self.turns_left
     }
 
+    /// The seats that still have a turn in the final round.
+    pub fn final_turns(&self) -> &[Seat] {
+        &self.final_turns
+    }
+
     pub fn score(&self) -> usize {
         debug!("Calculate final score");
         self.played_cards
@@ -328,20 +641,302 @@ impl GameState {
     }
 }
 
-pub enum CardPlayingResult {
-    Ok {
+fn cards_per_player(no_of_players: usize) -> usize {
+    match no_of_players >= FOUR_CARDS_THRESHOLD {
+        false => 5,
+        true  => 4,
+    }
+}
+
+/// A turn a player takes, as applied to the game state by `GameState::apply`.
+#[derive(Clone, PartialEq)]
+pub enum Action {
+    Discard    { player: Seat, card_id: usize },
+    Play       { player: Seat, card_id: usize },
+    HintColor  { player: Seat, target: Seat, color: Color },
+    HintNumber { player: Seat, target: Seat, number: Number },
+}
+
+impl Action {
+    /// The player taking the action.
+    pub fn player(&self) -> Seat {
+        match *self {
+            Action::Discard    { player, .. } |
+            Action::Play       { player, .. } |
+            Action::HintColor  { player, .. } |
+            Action::HintNumber { player, .. } => player,
+        }
+    }
+}
+
+pub enum Outcome {
+    Discarded {
+        slot:           usize,
+        discarded_card: Card,
+        drawn_card:     Option<Card>,
+    },
+    Played {
+        slot:        usize,
         success:     bool,
         played_card: Card,
         drawn_card:  Option<Card>,
     },
-    EpicFail,
-    Err(&'static str),
+    Hinted,
 }
 
-pub enum DiscardCardResult {
-    Ok {
-        discarded_card: Card,
-        drawn_card:     Option<Card>
-    },
-    Err(&'static str),
+/// Why an action is not allowed by the rules of the game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RuleError {
+    GameNotStarted,
+    GameIsOver,
+    NotYourTurn,
+    PlayerNotFound,
+    CardNotFound,
+    NoHintTokens,
+    HintToSelf,
+    InvalidSlot,
+}
+
+impl RuleError {
+    pub fn explanation(&self) -> &'static str {
+        match *self {
+            RuleError::GameNotStarted => GAME_NOT_STARTED,
+            RuleError::GameIsOver     => GAME_IS_OVER,
+            RuleError::NotYourTurn    => NOT_YOUR_TURN,
+            RuleError::PlayerNotFound => PLAYER_NOT_FOUND,
+            RuleError::CardNotFound   => CARD_NOT_FOUND,
+            RuleError::NoHintTokens   => NO_HINT_TOKENS,
+            RuleError::HintToSelf     => HINT_TO_SELF,
+            RuleError::InvalidSlot    => INVALID_SLOT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cards::{Card, Color, Number};
+    use serialization::to_json;
+
+    /// Two players with no error tokens and a deck of eleven red twos, none of which can be played.
+    /// After dealing a single card is left to draw.
+    fn final_round_game() -> GameState {
+        let deck = (1..12).map(|id| Card::new(id, Color::Red, Number::Two)).collect();
+        let mut game_state = GameState::with_deck(DEFAULT_HINT_TOKENS, 0, [0; 8], deck);
+        game_state.add_player("alice").unwrap();
+        game_state.add_player("bob").unwrap();
+        game_state.start().unwrap();
+        game_state
+    }
+
+    fn lobby(seed: Seed, names: &[&str]) -> GameState {
+        let mut game_state = GameState::with_deck(DEFAULT_HINT_TOKENS, DEFAULT_ERR_TOKENS, seed, cards::new_deck(&seed));
+        for name in names {
+            game_state.add_player(name).unwrap();
+        }
+        game_state
+    }
+
+    fn started(names: &[&str]) -> GameState {
+        let mut game_state = lobby([0; 8], names);
+        game_state.start().unwrap();
+        game_state
+    }
+
+    fn first_card(game_state: &GameState, player: Seat) -> usize {
+        game_state.players()[player].cards[0].card.id
+    }
+
+    #[test]
+    fn strike_out_on_last_final_round_turn() {
+        let mut game_state = final_round_game();
+        let card_id = first_card(&game_state, 0);
+        game_state.apply(&Action::Discard { player: 0, card_id: card_id }).unwrap();
+        assert_eq!(game_state.phase(), GamePhase::FinalRound);
+        let card_id = first_card(&game_state, 1);
+        game_state.apply(&Action::Discard { player: 1, card_id: card_id }).unwrap();
+        assert_eq!(game_state.turns_left(), Some(1));
+
+        let card_id = first_card(&game_state, 0);
+        match game_state.apply(&Action::Play { player: 0, card_id: card_id }) {
+            Ok(Outcome::Played { success: false, .. }) => {}
+            _                                          => panic!("the misplay was not applied"),
+        }
+        assert_eq!(game_state.phase(), GamePhase::Finished { reason: FinishReason::Strikes });
+    }
+
+    #[test]
+    fn final_round_ends_after_every_player_had_a_turn() {
+        let mut game_state = final_round_game();
+        for &player in &[0, 1, 0] {
+            let card_id = first_card(&game_state, player);
+            game_state.apply(&Action::Discard { player: player, card_id: card_id }).unwrap();
+        }
+        assert_eq!(game_state.phase(), GamePhase::Finished { reason: FinishReason::FinalRoundOver });
+    }
+
+    #[test]
+    fn seats_are_shuffled_by_the_seed() {
+        let names = ["alice", "bob", "carol", "dave", "eve"];
+        let seating = |seed| {
+            let mut game_state = lobby(seed, &names);
+            game_state.shuffle_seats().unwrap();
+            (game_state.player_names().iter().map(|n| n.to_string()).collect::<Vec<_>>(), game_state.first_player())
+        };
+        assert_eq!(seating([7; 8]), seating([7; 8]));
+        assert!((0..8).any(|word| seating([word; 8]) != seating([7; 8])));
+        let (mut order, _) = seating([7; 8]);
+        order.sort();
+        assert_eq!(order, names);
+    }
+
+    #[test]
+    fn rejected_start_changes_nothing() {
+        let mut game_state = lobby([0; 8], &[]);
+        assert_eq!(game_state.check_start(), Err(NO_CARDS));
+        assert_eq!(game_state.start(), Err(NO_CARDS));
+        assert_eq!(game_state.phase(), GamePhase::Lobby);
+        game_state.add_player("alice").unwrap();
+        assert_eq!(game_state.check_start(), Ok(()));
+    }
+
+    #[test]
+    fn validate_changes_nothing() {
+        let game_state = started(&["alice", "bob"]);
+        let before = to_json(&game_state);
+        let card_id = first_card(&game_state, 0);
+        for action in &[Action::Play { player: 0, card_id: card_id },
+                        Action::Discard { player: 0, card_id: card_id },
+                        Action::HintColor { player: 0, target: 1, color: Color::Red },
+                        Action::Play { player: 1, card_id: card_id }] {
+            game_state.validate(action).unwrap_or(());
+        }
+        assert_eq!(to_json(&game_state), before);
+    }
+
+    #[test]
+    fn rule_errors() {
+        let game_state = lobby([0; 8], &["alice", "bob"]);
+        assert_eq!(game_state.validate(&Action::Discard { player: 0, card_id: 0 }), Err(RuleError::GameNotStarted));
+
+        let mut game_state = started(&["alice", "bob"]);
+        let bobs_card = first_card(&game_state, 1);
+        assert_eq!(game_state.validate(&Action::Play { player: 1, card_id: bobs_card }), Err(RuleError::NotYourTurn));
+        assert_eq!(game_state.validate(&Action::Play { player: 0, card_id: bobs_card }), Err(RuleError::CardNotFound));
+        assert_eq!(game_state.validate(&Action::Play { player: 2, card_id: bobs_card }), Err(RuleError::PlayerNotFound));
+        assert_eq!(game_state.validate(&Action::HintColor { player: 0, target: 0, color: Color::Red }), Err(RuleError::HintToSelf));
+        assert_eq!(game_state.validate(&Action::HintNumber { player: 0, target: 2, number: Number::One }), Err(RuleError::PlayerNotFound));
+
+        // hints may be negative, so a hint that touches no card is still allowed
+        let reds = final_round_game();
+        let hint = Action::HintColor { player: 0, target: 1, color: Color::White };
+        assert_eq!(reds.validate(&hint), Ok(()));
+        assert!(reds.touched_cards(&hint).is_empty());
+
+        for turn in 0..DEFAULT_HINT_TOKENS {
+            let player = turn % 2;
+            game_state.apply(&Action::HintNumber { player: player, target: 1 - player, number: Number::One }).unwrap();
+        }
+        assert_eq!(game_state.hint_tokens(), 0);
+        assert_eq!(game_state.validate(&Action::HintNumber { player: 0, target: 1, number: Number::One }), Err(RuleError::NoHintTokens));
+
+        let mut game_state = final_round_game();
+        for &player in &[0, 1, 0] {
+            let card_id = first_card(&game_state, player);
+            game_state.apply(&Action::Discard { player: player, card_id: card_id }).unwrap();
+        }
+        let card_id = first_card(&game_state, 1);
+        assert_eq!(game_state.validate(&Action::Discard { player: 1, card_id: card_id }), Err(RuleError::GameIsOver));
+    }
+
+    #[test]
+    fn legal_actions_are_the_valid_ones() {
+        let mut game_state = started(&["alice", "bob", "carol"]);
+        let hand_size = game_state.players()[0].cards.len();
+        let legal = game_state.legal_actions(0);
+        assert_eq!(legal.len(), 2 * hand_size + 2 * (ALL_COLORS.len() + ALL_NUMBERS.len()));
+        assert!(legal.iter().all(|action| action.player() == 0 && game_state.validate(action).is_ok()));
+        assert!(game_state.legal_actions(1).is_empty());
+
+        for turn in 0..DEFAULT_HINT_TOKENS {
+            let player = turn % 3;
+            game_state.apply(&Action::HintColor { player: player, target: (player + 1) % 3, color: Color::Red }).unwrap();
+        }
+        let player = DEFAULT_HINT_TOKENS % 3;
+        let legal = game_state.legal_actions(player);
+        assert_eq!(legal.len(), 2 * game_state.players()[player].cards.len());
+        assert!(legal.iter().all(|action| game_state.validate(action).is_ok()));
+    }
+
+    fn hand(game_state: &GameState, player: Seat) -> Vec<usize> {
+        game_state.players()[player].cards.iter().map(|c| c.card.id).collect()
+    }
+
+    #[test]
+    fn dealt_hands_hold_the_newest_card_left() {
+        let game_state = final_round_game();
+        assert_eq!(hand(&game_state, 0), vec![5, 4, 3, 2, 1]);
+        assert_eq!(hand(&game_state, 1), vec![10, 9, 8, 7, 6]);
+    }
+
+    #[test]
+    fn draws_go_into_the_draw_slot() {
+        let mut game_state = final_round_game();
+        match game_state.apply(&Action::Discard { player: 0, card_id: 3 }).unwrap() {
+            Outcome::Discarded { slot, drawn_card: Some(drawn), .. } => {
+                assert_eq!(slot, 2);
+                assert_eq!(drawn.id, 11);
+            }
+            _ => panic!("no card was drawn"),
+        }
+        assert_eq!(hand(&game_state, 0), vec![11, 5, 4, 2, 1]);
+        assert_eq!(game_state.slot(0, 11), Some(DRAW_SLOT));
+    }
+
+    #[test]
+    fn slots_and_card_ids_name_the_same_card() {
+        let game_state = final_round_game();
+        for (slot, card_id) in hand(&game_state, 1).into_iter().enumerate() {
+            assert_eq!(game_state.card_at(1, slot), Some(card_id));
+            assert_eq!(game_state.slot(1, card_id), Some(slot));
+        }
+        assert_eq!(game_state.card_at(1, 5), None);
+        assert_eq!(game_state.slot(1, 1), None);
+
+        let mut id_game = final_round_game();
+        let mut slot_game = final_round_game();
+        let discarded = |outcome| match outcome {
+            Outcome::Discarded { slot, discarded_card, .. } => (slot, discarded_card.id),
+            _                                               => panic!("the card was not discarded"),
+        };
+        let by_id = discarded(id_game.apply(&Action::Discard { player: 0, card_id: 4 }).unwrap());
+        let card_id = slot_game.card_at(0, 1).unwrap();
+        let by_slot = discarded(slot_game.apply(&Action::Discard { player: 0, card_id: card_id }).unwrap());
+        assert_eq!(by_id, by_slot);
+        assert_eq!(by_slot, (1, 4));
+    }
+
+    #[test]
+    fn cards_move_within_their_hand() {
+        let mut game_state = final_round_game();
+        assert_eq!(game_state.move_card(0, 3, 0), Ok(2));
+        assert_eq!(hand(&game_state, 0), vec![3, 5, 4, 2, 1]);
+        assert_eq!(game_state.move_card(0, 3, 4), Ok(0));
+        assert_eq!(hand(&game_state, 0), vec![5, 4, 2, 1, 3]);
+        // moving is not a turn, so it is allowed whoever's turn it is and passes none
+        assert_eq!(game_state.move_card(1, 6, 0), Ok(4));
+        assert_eq!(hand(&game_state, 1), vec![6, 10, 9, 8, 7]);
+        assert_eq!(game_state.next_player(), Some(0));
+        assert_eq!(game_state.turn(), 0);
+    }
+
+    #[test]
+    fn moves_are_limited_to_the_own_hand() {
+        let mut game_state = final_round_game();
+        assert_eq!(game_state.move_card(0, 3, 5), Err(RuleError::InvalidSlot));
+        assert_eq!(game_state.move_card(0, 6, 0), Err(RuleError::CardNotFound));
+        assert_eq!(game_state.move_card(2, 3, 0), Err(RuleError::PlayerNotFound));
+        assert_eq!(hand(&game_state, 0), vec![5, 4, 3, 2, 1]);
+        assert_eq!(hand(&game_state, 1), vec![10, 9, 8, 7, 6]);
+    }
 }
@@ -0,0 +1,93 @@
+use rustc_serialize::json;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use server::Server;
+use game_state::Void;
+
+const JSON_TYPE:      &'static str = "application/json";
+const TEXT_TYPE:      &'static str = "text/plain; charset=utf-8";
+const METRICS_TYPE:   &'static str = "text/plain; version=0.0.4";
+/// Seconds a client may take to send its request or receive the response.
+const IO_TIMEOUT:     u64          = 5;
+/// Longest request line and headers taken together, anything beyond is not buffered.
+const MAX_HEAD_BYTES: u64          = 8 * 1024;
+
+/// A minimal HTTP/1.1 interface for monitoring: `/health`, `/rooms` and `/metrics`.
+/// Every request is answered on its own thread, so a stalled client cannot hold up health checks.
+pub fn listen(addr: &str, server: Arc<Mutex<Server>>) -> io::Result<Void> {
+    let listener = try!(TcpListener::bind(addr));
+    info!("Start listening for incoming HTTP requests on {}.", addr);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let server = server.clone();
+            thread::spawn(move || {
+                if let Err(e) = stream.and_then(|stream| serve(stream, &server)) {
+                    error!("Could not answer HTTP request: {}.", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn serve(mut stream: TcpStream, server: &Mutex<Server>) -> io::Result<Void> {
+    try!(stream.set_read_timeout(Some(Duration::from_secs(IO_TIMEOUT))));
+    try!(stream.set_write_timeout(Some(Duration::from_secs(IO_TIMEOUT))));
+    let request_line = match read_head(&stream) {
+        Ok(Some(line))                                        => line,
+        Ok(None)                                              => {
+            return respond(&mut stream, "431 Request Header Fields Too Large", TEXT_TYPE, "The request head is too large.\n");
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+            return respond(&mut stream, "400 Bad Request", TEXT_TYPE, "The request head is not valid UTF-8.\n");
+        }
+        Err(e)                                                => return Err(e),
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    debug!("HTTP request {} {}.", method, path);
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", TEXT_TYPE, "Only GET is supported.\n");
+    }
+    match path {
+        "/health"  => respond(&mut stream, "200 OK", TEXT_TYPE, "OK\n"),
+        "/rooms"   => {
+            let rooms = json::encode(&server.lock().unwrap().rooms()).unwrap();
+            respond(&mut stream, "200 OK", JSON_TYPE, &rooms)
+        }
+        "/metrics" => {
+            let metrics = server.lock().unwrap().metrics();
+            respond(&mut stream, "200 OK", METRICS_TYPE, &metrics)
+        }
+        _          => respond(&mut stream, "404 Not Found", TEXT_TYPE, "Not found.\n"),
+    }
+}
+
+/// Reads the request line and skips the headers, `None` if they do not end within `MAX_HEAD_BYTES`.
+fn read_head(stream: &TcpStream) -> io::Result<Option<String>> {
+    let mut reader = BufReader::new(stream).take(MAX_HEAD_BYTES);
+    let mut request_line = String::new();
+    try!(reader.read_line(&mut request_line));
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if try!(reader.read_line(&mut header)) == 0 || header.trim() == "" {
+            break;
+        }
+    }
+    if reader.limit() == 0 && !header.ends_with('\n') {
+        return Ok(None);
+    }
+    Ok(Some(request_line))
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<Void> {
+    try!(write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, content_type, body.len(), body));
+    stream.flush()
+}
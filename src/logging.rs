@@ -0,0 +1,172 @@
+use log;
+use log::{LogLevelFilter, LogMetadata, LogRecord};
+use env_logger;
+use rustc_serialize::json::{Json, Object};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::io;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use requests::RequestType;
+use storage::LoggedAction;
+use serialization::to_json;
+
+const LOG_LEVEL_VAR: &'static str = "RUST_LOG";
+const EVENT_TARGET:  &'static str = "hanabi::events";
+
+static JSON_LOGGING: AtomicBool = AtomicBool::new(false);
+
+thread_local!(static CONTEXT: RefCell<LogContext> = RefCell::new(Default::default()));
+
+/// What a log record is about, attached to every record written while the context is set.
+#[derive(Default, Clone)]
+pub struct LogContext {
+    pub room_id:       Option<String>,
+    pub connection_id: Option<usize>,
+    pub player:        Option<String>,
+    pub request_type:  Option<RequestType>,
+}
+
+/// Sets the context of all records logged by the current thread until it is cleared again.
+pub fn set_context(context: LogContext) {
+    CONTEXT.with(|c| *c.borrow_mut() = context);
+}
+
+pub fn clear_context() {
+    set_context(Default::default());
+}
+
+/// Key events of a game, which structured logs emit as typed records.
+pub enum GameEvent<'e> {
+    Action(&'e LoggedAction),
+    GameOver { score: usize },
+}
+
+impl<'e> GameEvent<'e> {
+    fn name(&self) -> &'static str {
+        match *self {
+            GameEvent::Action(&LoggedAction::Join { .. })  => "PLAYER_JOINED",
+            GameEvent::Action(&LoggedAction::Seats { .. }) => "SEATS_ARRANGED",
+            GameEvent::Action(&LoggedAction::Start)        => "GAME_STARTED",
+            GameEvent::Action(&LoggedAction::Kick { .. })  => "PLAYER_KICKED",
+            GameEvent::Action(_)                           => "ACTION",
+            GameEvent::GameOver { .. }                     => "GAME_OVER",
+        }
+    }
+
+    fn fields(&self) -> Object {
+        let mut fields = BTreeMap::new();
+        {
+            let mut field = |name: &str, value: Json| { fields.insert(name.to_owned(), value); };
+            match *self {
+                GameEvent::Action(&LoggedAction::Join { ref name }) => field("joining_player", Json::String(name.clone())),
+                GameEvent::Action(&LoggedAction::Seats { ref names, ref first_player }) => {
+                    field("seat_order", Json::Array(names.iter().map(|n| Json::String(n.clone())).collect()));
+                    field("first_player", Json::String(first_player.clone()));
+                }
+                GameEvent::Action(&LoggedAction::Start) => {}
+                GameEvent::Action(&LoggedAction::Kick { ref name }) => field("kicked_player", Json::String(name.clone())),
+                GameEvent::Action(&LoggedAction::Discard { ref player, card_id }) => {
+                    field("action", Json::String("DISCARD".to_owned()));
+                    field("acting_player", Json::String(player.clone()));
+                    field("card_id", Json::U64(card_id as u64));
+                }
+                GameEvent::Action(&LoggedAction::Play { ref player, card_id }) => {
+                    field("action", Json::String("PLAY".to_owned()));
+                    field("acting_player", Json::String(player.clone()));
+                    field("card_id", Json::U64(card_id as u64));
+                }
+                GameEvent::Action(&LoggedAction::HintColor { ref player, ref target, ref color }) => {
+                    field("action", Json::String("HINT_COLOR".to_owned()));
+                    field("acting_player", Json::String(player.clone()));
+                    field("target_player", Json::String(target.clone()));
+                    field("color", to_json(color));
+                }
+                GameEvent::Action(&LoggedAction::HintNumber { ref player, ref target, ref number }) => {
+                    field("action", Json::String("HINT_NUMBER".to_owned()));
+                    field("acting_player", Json::String(player.clone()));
+                    field("target_player", Json::String(target.clone()));
+                    field("number", to_json(number));
+                }
+                GameEvent::Action(&LoggedAction::Move { ref player, card_id, to_slot }) => {
+                    field("action", Json::String("MOVE_CARD".to_owned()));
+                    field("acting_player", Json::String(player.clone()));
+                    field("card_id", Json::U64(card_id as u64));
+                    field("to_slot", Json::U64(to_slot as u64));
+                }
+                // the text is private to its author and stays out of the logs
+                GameEvent::Action(&LoggedAction::Note { ref player, card_id, .. }) => {
+                    field("action", Json::String("NOTE".to_owned()));
+                    field("acting_player", Json::String(player.clone()));
+                    field("card_id", Json::U64(card_id as u64));
+                }
+                GameEvent::GameOver { score } => field("score", Json::U64(score as u64)),
+            }
+        }
+        fields
+    }
+}
+
+/// Logs a game event, as a typed record with structured logging and as plain text otherwise.
+pub fn event(event: &GameEvent) {
+    if !JSON_LOGGING.load(Ordering::Relaxed) {
+        return info!("Game event {}: {}.", event.name(), Json::Object(event.fields()));
+    }
+    if log_enabled!(log::LogLevel::Info) {
+        let mut fields = event.fields();
+        fields.insert("event".to_owned(), Json::String(event.name().to_owned()));
+        write_record("INFO", EVENT_TARGET, &format!("Game event {}.", event.name()), fields);
+    }
+}
+
+/// Structured logging writes one json object per line to stderr. Its level is taken from
+/// `RUST_LOG` if that names a plain level and defaults to `info`.
+pub fn init(json: bool) {
+    if !json {
+        return env_logger::init().unwrap();
+    }
+    let level = env::var(LOG_LEVEL_VAR).ok().and_then(|l| l.parse().ok()).unwrap_or(LogLevelFilter::Info);
+    JSON_LOGGING.store(true, Ordering::Relaxed);
+    log::set_logger(|max_level| {
+        max_level.set(level);
+        Box::new(JsonLogger { level: level })
+    }).unwrap();
+}
+
+struct JsonLogger {
+    level: LogLevelFilter,
+}
+
+impl log::Log for JsonLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if self.enabled(record.metadata()) {
+            let level = record.level().to_string().to_uppercase();
+            write_record(&level, record.target(), &record.args().to_string(), BTreeMap::new());
+        }
+    }
+}
+
+fn write_record(level: &str, target: &str, message: &str, mut fields: Object) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| {
+        d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+    }).unwrap_or(0.0);
+    let context = CONTEXT.with(|c| c.borrow().clone());
+
+    fields.insert("timestamp".to_owned(), Json::F64(timestamp));
+    fields.insert("level".to_owned(), Json::String(level.to_owned()));
+    fields.insert("target".to_owned(), Json::String(target.to_owned()));
+    fields.insert("message".to_owned(), Json::String(message.to_owned()));
+    fields.insert("room_id".to_owned(), context.room_id.map_or(Json::Null, Json::String));
+    fields.insert("connection_id".to_owned(), context.connection_id.map_or(Json::Null, |id| Json::U64(id as u64)));
+    fields.insert("player".to_owned(), context.player.map_or(Json::Null, Json::String));
+    fields.insert("request_type".to_owned(), context.request_type.as_ref().map_or(Json::Null, to_json));
+
+    let stderr = io::stderr();
+    let _ = writeln!(stderr.lock(), "{}", Json::Object(fields));
+}
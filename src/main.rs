@@ -4,7 +4,13 @@ extern crate env_logger;
 extern crate rand;
 extern crate ws;
 extern crate rustc_serialize;
+extern crate crypto;
+extern crate libc;
+#[cfg(feature = "tls")]
+extern crate openssl;
 
+#[macro_use]
+mod schema;
 mod connection;
 mod server;
 mod requests;
@@ -13,26 +19,75 @@ mod game_state;
 mod cards;
 mod serialization;
 mod display;
+mod config;
+mod storage;
+mod accounts;
+mod stats;
+mod protocol;
+mod delta;
+mod msgpack;
+mod wire;
+mod websocket;
+mod tcp;
+mod tls;
+mod monitoring;
+mod http;
+mod logging;
+mod signals;
+mod notes;
 
-use std::rc::Rc;
-use std::cell::{Cell, RefCell};
-use connection::Connection;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use websocket::WebsocketHandler;
 use server::Server;
+use config::Config;
+
+const SCHEMA_FLAG: &'static str = "--schema";
+const WS_ADDR:     &'static str = "0.0.0.0:4444";
 
 fn main() {
 
-    env_logger::init().unwrap();
+    let config = Config::from_env();
+    logging::init(config.json_logs);
+
+    if env::args().skip(1).any(|arg| arg == SCHEMA_FLAG) {
+        println!("{}", schema::protocol_schema().pretty());
+        return;
+    }
+
+    let server = Arc::new(Mutex::new(Server::new(&config)));
+    signals::watch(server.clone(), Duration::from_secs(config.drain_secs));
+
+    if let Some(ref addr) = config.tcp_addr {
+        if let Err(e) = tcp::listen(addr, server.clone()) {
+            error!("Could not open TCP listener on {}: {}.", addr, e);
+        }
+    }
+
+    if let Some(ref addr) = config.http_addr {
+        if let Err(e) = http::listen(addr, server.clone()) {
+            error!("Could not open HTTP listener on {}: {}.", addr, e);
+        }
+    }
 
-    let server = Rc::new(RefCell::new(Server::new(Default::default())));
-    let token_cell = Cell::new(0);
+    let tls = match tls::from_config(&config) {
+        Ok(tls) => tls,
+        Err(e)  => return error!("Could not set up TLS: {}.", e),
+    };
+    let settings = ws::Settings {
+        encrypt_server: tls.is_some(),
+        ..Default::default()
+    };
 
     // TODO: factory?
-    info!("Start listening for incoming connections.");
-    ws::listen("0.0.0.0:4444", |out| {
-        let id = token_cell.get();
-        token_cell.set(id + 1);
-        Connection::new(id, out, server.clone())
-    }).unwrap_or_else( |_| {
+    info!("Start listening for incoming {} connections.", if tls.is_some() { "wss" } else { "ws" });
+    ws::Builder::new().with_settings(settings).build(|out| {
+        WebsocketHandler::new(out, tls.clone(), server.clone())
+    }).and_then(|ws| {
+        server.lock().unwrap().set_control(ws.broadcaster());
+        ws.listen(WS_ADDR)
+    }).map(|_| ()).unwrap_or_else( |_| {
         error!("Could not open server.");
         ()
     });
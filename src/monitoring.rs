@@ -0,0 +1,78 @@
+use rustc_serialize::json;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use responses::ErrorCode;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RoomStatus {
+    Waiting,
+    Running,
+    Finished,
+}
+
+protocol_struct! {
+    /// A game as listed to the dashboards.
+    #[derive(RustcEncodable)]
+    pub struct RoomInfo {
+        pub room_id:       String,
+        pub status:        RoomStatus,
+        pub players:       Vec<String>,
+        pub connections:   usize,
+        pub state_version: usize,
+        pub score:         usize,
+    }
+}
+
+/// Counters exported in Prometheus text format. They only cover the lifetime of the process.
+/// Errors are answered from `&self` contexts, hence the interior mutability.
+#[derive(Default)]
+pub struct Metrics {
+    games_started:  Cell<usize>,
+    games_finished: Cell<usize>,
+    score_sum:      Cell<usize>,
+    errors:         RefCell<BTreeMap<ErrorCode, usize>>,
+}
+
+impl Metrics {
+    pub fn game_started(&self) {
+        self.games_started.set(self.games_started.get() + 1);
+    }
+
+    pub fn game_finished(&self, score: usize) {
+        self.games_finished.set(self.games_finished.get() + 1);
+        self.score_sum.set(self.score_sum.get() + score);
+    }
+
+    pub fn error(&self, code: ErrorCode) {
+        *self.errors.borrow_mut().entry(code).or_insert(0) += 1;
+    }
+
+    pub fn render(&self, connections: usize) -> String {
+        let finished = self.games_finished.get();
+        let average_score = if finished == 0 { 0.0 } else { self.score_sum.get() as f64 / finished as f64 };
+
+        let mut out = String::new();
+        gauge(&mut out, "hanabi_connections", "Currently open client connections.", connections as f64);
+        counter(&mut out, "hanabi_games_started_total", "Games started.", self.games_started.get());
+        counter(&mut out, "hanabi_games_finished_total", "Games finished.", finished);
+        counter(&mut out, "hanabi_score_sum", "Sum of the final scores of all finished games.", self.score_sum.get());
+        gauge(&mut out, "hanabi_average_score", "Average final score of all finished games.", average_score);
+
+        writeln!(out, "# HELP hanabi_request_errors_total Error responses sent, by error code.").unwrap();
+        writeln!(out, "# TYPE hanabi_request_errors_total counter").unwrap();
+        for (code, count) in self.errors.borrow().iter() {
+            // the json encoding of an error code is its quoted name, just what a label value needs
+            writeln!(out, "hanabi_request_errors_total{{error={}}} {}", json::encode(code).unwrap(), count).unwrap();
+        }
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value).unwrap();
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: usize) {
+    writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value).unwrap();
+}
@@ -0,0 +1,265 @@
+use rustc_serialize::json::{Json, Object};
+use std::collections::BTreeMap;
+use std::str;
+
+/// Deepest nesting of arrays and maps that is decoded, far beyond any request but low enough that a
+/// crafted message cannot exhaust the stack.
+const MAX_DEPTH: usize = 32;
+
+/// Why a binary message could not be read as MessagePack.
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    UnsupportedType(u8),
+    InvalidUtf8,
+    NonStringKey,
+    TrailingBytes,
+    TooDeep,
+}
+
+/// Encodes a json value as MessagePack, so both encodings share the same logical schema.
+pub fn encode(json: &Json) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_value(&mut buf, json);
+    buf
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Json, DecodeError> {
+    let mut reader = Reader {
+        bytes: bytes,
+        pos:   0,
+        depth: 0,
+    };
+    let json = try!(reader.read_value());
+    if reader.pos != bytes.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(json)
+}
+
+fn write_value(buf: &mut Vec<u8>, json: &Json) {
+    match *json {
+        Json::Null              => buf.push(0xc0),
+        Json::Boolean(false)    => buf.push(0xc2),
+        Json::Boolean(true)     => buf.push(0xc3),
+        Json::U64(n)            => write_uint(buf, n),
+        Json::I64(n) if n >= 0  => write_uint(buf, n as u64),
+        Json::I64(n)            => write_int(buf, n),
+        Json::F64(f)            => {
+            buf.push(0xcb);
+            write_be(buf, f.to_bits(), 8);
+        }
+        Json::String(ref s)     => {
+            write_str_len(buf, s.len());
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Json::Array(ref values) => {
+            write_container_len(buf, values.len(), 0x90, 0xdc);
+            for value in values {
+                write_value(buf, value);
+            }
+        }
+        Json::Object(ref obj)   => {
+            write_container_len(buf, obj.len(), 0x80, 0xde);
+            for (key, value) in obj {
+                write_value(buf, &Json::String(key.clone()));
+                write_value(buf, value);
+            }
+        }
+    }
+}
+
+fn write_uint(buf: &mut Vec<u8>, n: u64) {
+    if n < 0x80 {
+        buf.push(n as u8);
+    } else if n <= 0xff {
+        buf.push(0xcc);
+        write_be(buf, n, 1);
+    } else if n <= 0xffff {
+        buf.push(0xcd);
+        write_be(buf, n, 2);
+    } else if n <= 0xffff_ffff {
+        buf.push(0xce);
+        write_be(buf, n, 4);
+    } else {
+        buf.push(0xcf);
+        write_be(buf, n, 8);
+    }
+}
+
+fn write_int(buf: &mut Vec<u8>, n: i64) {
+    if n >= -32 {
+        buf.push(n as i8 as u8);
+    } else if n >= -0x80 {
+        buf.push(0xd0);
+        write_be(buf, n as u64, 1);
+    } else if n >= -0x8000 {
+        buf.push(0xd1);
+        write_be(buf, n as u64, 2);
+    } else if n >= -0x8000_0000 {
+        buf.push(0xd2);
+        write_be(buf, n as u64, 4);
+    } else {
+        buf.push(0xd3);
+        write_be(buf, n as u64, 8);
+    }
+}
+
+fn write_str_len(buf: &mut Vec<u8>, len: usize) {
+    if len < 32 {
+        buf.push(0xa0 | len as u8);
+    } else if len <= 0xff {
+        buf.push(0xd9);
+        write_be(buf, len as u64, 1);
+    } else if len <= 0xffff {
+        buf.push(0xda);
+        write_be(buf, len as u64, 2);
+    } else {
+        buf.push(0xdb);
+        write_be(buf, len as u64, 4);
+    }
+}
+
+/// Arrays and maps share their layout: a fix variant for up to 15 elements,
+/// otherwise `marker` followed by a 16 bit or `marker + 1` followed by a 32 bit length.
+fn write_container_len(buf: &mut Vec<u8>, len: usize, fix_marker: u8, marker: u8) {
+    if len < 16 {
+        buf.push(fix_marker | len as u8);
+    } else if len <= 0xffff {
+        buf.push(marker);
+        write_be(buf, len as u64, 2);
+    } else {
+        buf.push(marker + 1);
+        write_be(buf, len as u64, 4);
+    }
+}
+
+fn write_be(buf: &mut Vec<u8>, n: u64, bytes: usize) {
+    for i in (0..bytes).rev() {
+        buf.push((n >> (8 * i)) as u8);
+    }
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos:   usize,
+    depth: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8], DecodeError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let taken = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn read_be(&mut self, len: usize) -> Result<u64, DecodeError> {
+        let bytes = try!(self.take(len));
+        Ok(bytes.iter().fold(0, |n, &b| (n << 8) | b as u64))
+    }
+
+    /// Reads a big endian two's complement integer of `len` bytes.
+    fn read_signed(&mut self, len: usize) -> Result<i64, DecodeError> {
+        let shift = 64 - 8 * len;
+        Ok(((try!(self.read_be(len)) << shift) as i64) >> shift)
+    }
+
+    fn read_value(&mut self) -> Result<Json, DecodeError> {
+        let marker = try!(self.take(1))[0];
+        match marker {
+            0x00..=0x7f => Ok(Json::U64(marker as u64)),
+            0x80..=0x8f => self.read_map((marker & 0x0f) as usize),
+            0x90..=0x9f => self.read_array((marker & 0x0f) as usize),
+            0xa0..=0xbf => self.read_str((marker & 0x1f) as usize),
+            0xc0        => Ok(Json::Null),
+            0xc2        => Ok(Json::Boolean(false)),
+            0xc3        => Ok(Json::Boolean(true)),
+            0xca        => Ok(Json::F64(f32::from_bits(try!(self.read_be(4)) as u32) as f64)),
+            0xcb        => Ok(Json::F64(f64::from_bits(try!(self.read_be(8))))),
+            0xcc        => Ok(Json::U64(try!(self.read_be(1)))),
+            0xcd        => Ok(Json::U64(try!(self.read_be(2)))),
+            0xce        => Ok(Json::U64(try!(self.read_be(4)))),
+            0xcf        => Ok(Json::U64(try!(self.read_be(8)))),
+            0xd0        => Ok(Json::I64(try!(self.read_signed(1)))),
+            0xd1        => Ok(Json::I64(try!(self.read_signed(2)))),
+            0xd2        => Ok(Json::I64(try!(self.read_signed(4)))),
+            0xd3        => Ok(Json::I64(try!(self.read_signed(8)))),
+            0xd9        => { let len = try!(self.read_be(1)); self.read_str(len as usize) }
+            0xda        => { let len = try!(self.read_be(2)); self.read_str(len as usize) }
+            0xdb        => { let len = try!(self.read_be(4)); self.read_str(len as usize) }
+            0xdc        => { let len = try!(self.read_be(2)); self.read_array(len as usize) }
+            0xdd        => { let len = try!(self.read_be(4)); self.read_array(len as usize) }
+            0xde        => { let len = try!(self.read_be(2)); self.read_map(len as usize) }
+            0xdf        => { let len = try!(self.read_be(4)); self.read_map(len as usize) }
+            0xe0..=0xff => Ok(Json::I64(marker as i8 as i64)),
+            _           => Err(DecodeError::UnsupportedType(marker)),
+        }
+    }
+
+    fn read_str(&mut self, len: usize) -> Result<Json, DecodeError> {
+        let bytes = try!(self.take(len));
+        match str::from_utf8(bytes) {
+            Ok(s)  => Ok(Json::String(s.to_owned())),
+            Err(_) => Err(DecodeError::InvalidUtf8),
+        }
+    }
+
+    fn read_array(&mut self, len: usize) -> Result<Json, DecodeError> {
+        try!(self.descend());
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(try!(self.read_value()));
+        }
+        self.depth -= 1;
+        Ok(Json::Array(values))
+    }
+
+    fn read_map(&mut self, len: usize) -> Result<Json, DecodeError> {
+        try!(self.descend());
+        let mut obj: Object = BTreeMap::new();
+        for _ in 0..len {
+            let key = match try!(self.read_value()) {
+                Json::String(key) => key,
+                _                 => return Err(DecodeError::NonStringKey),
+            };
+            let value = try!(self.read_value());
+            obj.insert(key, value);
+        }
+        self.depth -= 1;
+        Ok(Json::Object(obj))
+    }
+
+    /// Enters an array or map, the caller leaves it again by decrementing `depth`.
+    fn descend(&mut self) -> Result<(), DecodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+    use super::*;
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| {
+            let mut bytes = vec![0x91; depth - 1];
+            bytes.push(0x90);
+            bytes
+        };
+        let mut expected = Json::Array(Vec::new());
+        for _ in 1..MAX_DEPTH {
+            expected = Json::Array(vec![expected]);
+        }
+        assert_eq!(decode(&nested(MAX_DEPTH)), Ok(expected));
+        assert_eq!(decode(&nested(MAX_DEPTH + 1)), Err(DecodeError::TooDeep));
+        assert_eq!(decode(&vec![0x91; 100000]), Err(DecodeError::TooDeep));
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+use game_state::Seat;
+
+/// Longest accepted note in characters, as stated in `NOTE_TOO_LONG`.
+pub const MAX_NOTE_LEN: usize = 200;
+
+protocol_struct! {
+    /// A player's private note on a card.
+    #[derive(RustcEncodable, Clone)]
+    pub struct CardNote {
+        pub author:  Seat,
+        pub card_id: usize,
+        pub text:    String,
+    }
+}
+
+/// The notes players attached to cards during a game. While the game is running a note is only
+/// shown to its author, once it is over all notes are revealed.
+#[derive(Default)]
+pub struct GameNotes {
+    notes: BTreeMap<(Seat, usize), String>,
+}
+
+impl GameNotes {
+    /// An empty text removes the note.
+    pub fn set(&mut self, author: Seat, card_id: usize, text: &str) {
+        if text.is_empty() {
+            self.notes.remove(&(author, card_id));
+        } else {
+            self.notes.insert((author, card_id), text.to_owned());
+        }
+    }
+
+    pub fn by(&self, author: Seat) -> Vec<CardNote> {
+        self.all().into_iter().filter(|note| note.author == author).collect()
+    }
+
+    pub fn all(&self) -> Vec<CardNote> {
+        self.notes.iter().map(|(&(author, card_id), text)| CardNote {
+            author:  author,
+            card_id: card_id,
+            text:    text.clone(),
+        }).collect()
+    }
+}
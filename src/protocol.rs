@@ -0,0 +1,188 @@
+use rustc_serialize::json::Json;
+use requests::RequestType;
+use requests::RequestType::*;
+
+/// The protocol version spoken by this server. Version 1 is the original protocol, version 2 added
+/// accounts, statistics and the handshake itself, version 3 added seats, syncing, administration,
+/// legal actions, rearranging cards and notes.
+pub const PROTOCOL_VERSION:     usize = 3;
+pub const MIN_PROTOCOL_VERSION: usize = 1;
+/// The first version that refers to players by their seat, older versions are sent their names.
+pub const SEATS_VERSION:        usize = 3;
+
+/// The response fields that hold a seat or a list of seats.
+const SEAT_FIELDS: &'static [&'static str] = &[
+    "author",
+    "discarding_player",
+    "final_turns",
+    "first_player",
+    "giver",
+    "hinting_player",
+    "host",
+    "moving_player",
+    "next_player",
+    "player",
+    "playing_player",
+    "target",
+    "target_player",
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Capability {
+    Accounts,
+    Stats,
+    Deltas,
+}
+
+pub const SUPPORTED_CAPABILITIES: &'static [Capability] = &[
+    Capability::Accounts,
+    Capability::Stats,
+    Capability::Deltas,
+];
+
+impl Capability {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Capability::Accounts => "ACCOUNTS",
+            Capability::Stats    => "STATS",
+            Capability::Deltas   => "DELTAS",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        SUPPORTED_CAPABILITIES.iter().find(|c| c.name() == name).cloned()
+    }
+}
+
+/// What a client and the server have agreed on during the handshake. The version decides which
+/// requests are accepted and whether responses refer to players by seat or by name.
+pub struct ClientProtocol {
+    pub version:      usize,
+    pub capabilities: Vec<Capability>,
+}
+
+impl ClientProtocol {
+    /// Negotiates the highest version both sides speak. Capabilities unknown to the server are dropped.
+    pub fn negotiate(version: usize, capabilities: &[String]) -> Option<Self> {
+        if version < MIN_PROTOCOL_VERSION {
+            return None;
+        }
+        Some(ClientProtocol {
+            version:      if version < PROTOCOL_VERSION { version } else { PROTOCOL_VERSION },
+            capabilities: capabilities.iter().filter_map(|c| Capability::from_name(c)).collect(),
+        })
+    }
+
+    pub fn has_seats(&self) -> bool {
+        self.version >= SEATS_VERSION
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    pub fn supports(&self, req_type: &RequestType) -> bool {
+        introduced_in(req_type) <= self.version && match required_capability(req_type) {
+            Some(capability) => self.has(capability),
+            None             => true,
+        }
+    }
+}
+
+fn introduced_in(req_type: &RequestType) -> usize {
+    match *req_type {
+        ConnectionRequestType   |
+        DiscardCardRequestType  |
+        HintColorRequestType    |
+        HintNumberRequestType   |
+        PlayCardRequestType     |
+        GameStartRequestType    => 1,
+        // renegotiating must always be possible
+        HelloRequestType        => MIN_PROTOCOL_VERSION,
+        RegisterRequestType     |
+        LoginRequestType        |
+        StatsRequestType        => 2,
+        SyncRequestType         |
+        AdminRequestType        |
+        LegalActionsRequestType |
+        SeatOrderRequestType    |
+        MoveCardRequestType     |
+        NoteRequestType         => 3,
+    }
+}
+
+fn required_capability(req_type: &RequestType) -> Option<Capability> {
+    match *req_type {
+        RegisterRequestType |
+        LoginRequestType    => Some(Capability::Accounts),
+        StatsRequestType    => Some(Capability::Stats),
+        _                   => None,
+    }
+}
+
+/// Rewrites a response for clients speaking a version before seats, replacing every seat with the
+/// name of the player sitting there. A game without a next player has an empty name as next player.
+pub fn with_names(msg: &Json, names: &[&str]) -> Json {
+    match *msg {
+        Json::Object(ref fields) => Json::Object(fields.iter().map(|(key, value)| {
+            let value = if SEAT_FIELDS.contains(&key.as_str()) {
+                name_seats(key, value, names)
+            } else {
+                with_names(value, names)
+            };
+            (key.clone(), value)
+        }).collect()),
+        Json::Array(ref values) => Json::Array(values.iter().map(|value| with_names(value, names)).collect()),
+        ref value               => value.clone(),
+    }
+}
+
+fn name_seats(key: &str, value: &Json, names: &[&str]) -> Json {
+    match *value {
+        Json::U64(seat)                    => names.get(seat as usize).map_or(value.clone(), |name| Json::String((*name).to_owned())),
+        Json::Array(ref seats)             => Json::Array(seats.iter().map(|seat| name_seats(key, seat, names)).collect()),
+        Json::Null if key == "next_player" => Json::String(String::new()),
+        ref value                          => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+    use super::*;
+
+    #[test]
+    fn requests_are_refused_before_their_version() {
+        let v2 = ClientProtocol::negotiate(2, &[]).unwrap();
+        let v3 = ClientProtocol::negotiate(4, &[]).unwrap();
+        assert_eq!(v3.version, PROTOCOL_VERSION);
+        assert!(v2.supports(&PlayCardRequestType) && v2.supports(&HelloRequestType));
+        for req_type in &[SyncRequestType, AdminRequestType, LegalActionsRequestType, SeatOrderRequestType,
+                          MoveCardRequestType, NoteRequestType] {
+            assert!(!v2.supports(req_type));
+            assert!(v3.supports(req_type));
+        }
+        assert!(!v3.supports(&StatsRequestType));
+        assert!(ClientProtocol::negotiate(0, &[]).is_none());
+    }
+
+    #[test]
+    fn older_versions_get_names() {
+        let names = ["alice", "bob"];
+        let resp = Json::from_str(r#"{
+            "msg_type": "HINT_COLOR_RESPONSE", "hinting_player": 0, "target_player": 1,
+            "game_state": {"players": [{"seat": 1, "name": "bob", "cards": [{"knowledge": {"clues": [{"giver": 0, "turn": 3}]}}]}],
+                           "next_player": 1, "final_turns": [1, 0], "host": null},
+            "state_delta": {"next_player": null, "moved_cards": [{"from": {"place": "DECK", "player": null},
+                                                                  "to":   {"place": "HAND", "player": 1}}]}
+        }"#).unwrap();
+        let expected = Json::from_str(r#"{
+            "msg_type": "HINT_COLOR_RESPONSE", "hinting_player": "alice", "target_player": "bob",
+            "game_state": {"players": [{"seat": 1, "name": "bob", "cards": [{"knowledge": {"clues": [{"giver": "alice", "turn": 3}]}}]}],
+                           "next_player": "bob", "final_turns": ["bob", "alice"], "host": null},
+            "state_delta": {"next_player": "", "moved_cards": [{"from": {"place": "DECK", "player": null},
+                                                                "to":   {"place": "HAND", "player": "bob"}}]}
+        }"#).unwrap();
+        assert_eq!(with_names(&resp, &names), expected);
+    }
+}
@@ -1,6 +1,8 @@
 use cards::{Color, Number};
+use game_state::Seat;
+use self::RequestType::*;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum RequestType {
     ConnectionRequestType,
     DiscardCardRequestType,
@@ -8,41 +10,185 @@ pub enum RequestType {
     HintNumberRequestType,
     PlayCardRequestType,
     GameStartRequestType,
+    RegisterRequestType,
+    LoginRequestType,
+    StatsRequestType,
+    HelloRequestType,
+    SyncRequestType,
+    AdminRequestType,
+    LegalActionsRequestType,
+    SeatOrderRequestType,
+    MoveCardRequestType,
+    NoteRequestType,
 }
 
-#[derive(RustcDecodable)]
-pub struct ConnectionRequest {
-    pub msg_type: RequestType,
-    pub name:     String
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AdminCommand {
+    ListRooms,
+    Inspect,
+    Kick,
+    EndGame,
+    Notice,
+    Shutdown,
 }
 
-#[derive(RustcDecodable)]
-pub struct DiscardCardRequest {
-    pub msg_type:          RequestType,
-    pub discarded_card_id: usize,
+/// Type and client-supplied id of the request currently being handled, echoed back in its responses.
+/// Both are read from the envelope of the request, which is why the requests below leave out the id:
+/// it is echoed even when the rest of the request cannot be decoded.
+#[derive(Default)]
+pub struct RequestContext {
+    pub req_type:   Option<RequestType>,
+    pub request_id: Option<String>,
 }
 
-#[derive(RustcDecodable)]
-pub struct HintColorRequest {
-    pub msg_type:      RequestType,
-    pub target_player: String,
-    pub color:         Color,
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct ConnectionRequest {
+        pub msg_type: RequestType = ConnectionRequestType,
+        pub name:     String,
+        pub token:    Option<String>,
+    }
 }
 
-#[derive(RustcDecodable)]
-pub struct HintNumberRequest {
-    pub msg_type:      RequestType,
-    pub target_player: String,
-    pub number:        Number,
+protocol_struct! {
+    /// The card is given either by its id or by its slot, counted from `0` at the left of the hand.
+    #[derive(RustcDecodable)]
+    pub struct DiscardCardRequest {
+        pub msg_type:          RequestType = DiscardCardRequestType,
+        pub discarded_card_id: Option<usize>,
+        pub slot:              Option<usize>,
+    }
 }
 
-#[derive(RustcDecodable)]
-pub struct PlayCardRequest {
-    pub msg_type:       RequestType,
-    pub played_card_id: usize,
+protocol_struct! {
+    /// The target is given by `target_seat`, addressing it by name through `target_player` is still
+    /// accepted until all clients have moved to seats.
+    #[derive(RustcDecodable)]
+    pub struct HintColorRequest {
+        pub msg_type:      RequestType = HintColorRequestType,
+        pub target_seat:   Option<Seat>,
+        pub target_player: Option<String>,
+        pub color:         Color,
+    }
 }
 
-#[derive(RustcDecodable)]
-pub struct GameStartRequest {
-    pub msg_type: RequestType,
+protocol_struct! {
+    /// Addresses its target like `HintColorRequest`.
+    #[derive(RustcDecodable)]
+    pub struct HintNumberRequest {
+        pub msg_type:      RequestType = HintNumberRequestType,
+        pub target_seat:   Option<Seat>,
+        pub target_player: Option<String>,
+        pub number:        Number,
+    }
+}
+
+protocol_struct! {
+    /// Addresses its card like `DiscardCardRequest`.
+    #[derive(RustcDecodable)]
+    pub struct PlayCardRequest {
+        pub msg_type:       RequestType = PlayCardRequestType,
+        pub played_card_id: Option<usize>,
+        pub slot:           Option<usize>,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct GameStartRequest {
+        pub msg_type: RequestType = GameStartRequestType,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct RegisterRequest {
+        pub msg_type: RequestType = RegisterRequestType,
+        pub name:     String,
+        pub password: String,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct LoginRequest {
+        pub msg_type: RequestType = LoginRequestType,
+        pub name:     String,
+        pub password: String,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct HelloRequest {
+        pub msg_type:         RequestType = HelloRequestType,
+        pub protocol_version: usize,
+        pub capabilities:     Vec<String>,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct SyncRequest {
+        pub msg_type: RequestType = SyncRequestType,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct LegalActionsRequest {
+        pub msg_type: RequestType = LegalActionsRequestType,
+    }
+}
+
+protocol_struct! {
+    /// `seats` lists the current seats in their new order, `first_player` is the new seat of the
+    /// player taking the first turn and defaults to `0`.
+    #[derive(RustcDecodable)]
+    pub struct SeatOrderRequest {
+        pub msg_type:     RequestType = SeatOrderRequestType,
+        pub seats:        Vec<Seat>,
+        pub first_player: Option<Seat>,
+    }
+}
+
+protocol_struct! {
+    /// The card to move is given like in `DiscardCardRequest`, `to_slot` is where it is put.
+    #[derive(RustcDecodable)]
+    pub struct MoveCardRequest {
+        pub msg_type: RequestType = MoveCardRequestType,
+        pub card_id:  Option<usize>,
+        pub slot:     Option<usize>,
+        pub to_slot:  usize,
+    }
+}
+
+protocol_struct! {
+    /// Attaches a private note to a card in any hand, an empty `text` removes the note.
+    #[derive(RustcDecodable)]
+    pub struct NoteRequest {
+        pub msg_type: RequestType = NoteRequestType,
+        pub card_id:  usize,
+        pub text:     String,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcDecodable)]
+    pub struct StatsRequest {
+        pub msg_type: RequestType = StatsRequestType,
+        pub name:     Option<String>,
+    }
+}
+
+protocol_struct! {
+    /// `message` is the notice to broadcast, or the reason given to the players for a kick, end of game or shutdown.
+    #[derive(RustcDecodable)]
+    pub struct AdminRequest {
+        pub msg_type:    RequestType = AdminRequestType,
+        pub admin_token: String,
+        pub command:     AdminCommand,
+        pub player:      Option<String>,
+        pub message:     Option<String>,
+    }
 }
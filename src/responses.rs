@@ -1,4 +1,10 @@
-use game_state::GameState;
+use game_state::{GameState, Seat, DRAW_SLOT};
+use notes::CardNote;
+use delta::{StateDelta, StateUpdate};
+use requests::{AdminCommand, RequestType};
+use monitoring::RoomInfo;
+use stats::PlayerStats;
+use protocol::{Capability, PROTOCOL_VERSION, MIN_PROTOCOL_VERSION};
 use cards::{Color, Number, Card};
 use self::ResponseType::*;
 
@@ -12,178 +18,581 @@ pub enum ResponseType {
     HintNumberResposeType,
     GameOverResponseType,
     GameStartResponseType,
+    LoginResponseType,
+    StatsResponseType,
+    HelloResponseType,
+    SyncResponseType,
+    AdminResponseType,
+    NoticeResponseType,
+    LegalActionsResponseType,
+    SeatOrderResponseType,
+    MoveCardResponseType,
+    NoteResponseType,
 }
 
-#[derive(RustcEncodable)]
-pub struct ErrorResponse {
-    msg_type:    ResponseType,
-    explanation: &'static str,
-    err_details: Option<String>,
+/// Stable, machine-readable counterpart of the explanations in `error_messages`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorCode {
+    MsgToTxtError,
+    UnableToGetMsgType,
+    UnableToDeserializePayload,
+    InternalError,
+    PlayerAlreadyExists,
+    AlreadyConnected,
+    ConnGameAlreadyStarted,
+    GameAlreadyStarted,
+    NotYetConnected,
+    NoCards,
+    NoHintTokens,
+    CardNotFound,
+    PlayerNotFound,
+    GameIsOver,
+    NotYourTurn,
+    AccountsDisabled,
+    AccountAlreadyExists,
+    AccountNotSaved,
+    InvalidCredentials,
+    LoginRequired,
+    InvalidSession,
+    StatsDisabled,
+    StatsUnavailable,
+    IncompatibleProtocolVersion,
+    UnsupportedRequest,
+    AdminDisabled,
+    InvalidAdminToken,
+    ServerDraining,
+    GameNotStarted,
+    HintToSelf,
+    NotHost,
+    InvalidSeatOrder,
+    InvalidSlot,
+    NoteTooLong,
+    PlayerKicked,
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct ErrorResponse {
+        msg_type:     ResponseType = ErrorResponseType,
+        error_code:   ErrorCode,
+        explanation:  &'static str,
+        err_details:  Option<String>,
+        request_type: Option<RequestType>,
+        request_id:   Option<String>,
+    }
 }
 
 impl ErrorResponse {
-    pub fn new(explanation: &'static str, err_details: Option<&str>) -> Self {
+    pub fn new(explanation: &'static str,
+               err_details: Option<&str>,
+               request_type: Option<RequestType>,
+               request_id: Option<&str>)
+               -> Self {
         ErrorResponse {
-            msg_type:    ErrorResponseType,
-            explanation: explanation,
-            err_details: match err_details {
-                Some(details) => Some(details.to_owned()),
-                None          => None,
-            }
+            msg_type:     ErrorResponseType,
+            error_code:   error_messages::error_code(explanation),
+            explanation:  explanation,
+            err_details:  err_details.map(|details| details.to_owned()),
+            request_type: request_type,
+            request_id:   request_id.map(|id| id.to_owned()),
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct ConnectionResponse<'s> {
-    msg_type: ResponseType,
-    names:    Vec<&'s str>,
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct ConnectionResponse<'s> {
+        msg_type:   ResponseType = ConnectionResponseType,
+        names:      Vec<&'s str>,
+        request_id: Option<&'s str>,
+    }
 }
 
 impl<'s> ConnectionResponse<'s> {
-    pub fn new(names: Vec<&'s str>) -> Self {
+    pub fn new(names: Vec<&'s str>, request_id: Option<&'s str>) -> Self {
         ConnectionResponse {
-            msg_type: ConnectionResponseType,
-            names:    names,
+            msg_type:   ConnectionResponseType,
+            names:      names,
+            request_id: request_id,
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct DiscardCardResponse<'s> {
-    msg_type:          ResponseType,
-    discarding_player: &'s str,
-    discarded_card:    &'s Card,
-    drawn_card:        Option<&'s Card>,
-    game_state:        &'s GameState,
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct DiscardCardResponse<'s> {
+        msg_type:          ResponseType = DiscardCardResponseType,
+        discarding_player: Seat,
+        discarded_slot:    usize,
+        discarded_card:    &'s Card,
+        drawn_card:        Option<&'s Card>,
+        drawn_slot:        Option<usize>,
+        game_state:        Option<&'s GameState>,
+        state_delta:       Option<&'s StateDelta>,
+        state_version:     usize,
+        request_id:        Option<&'s str>,
+    }
 }
 
 impl<'s> DiscardCardResponse<'s> {
-    pub fn new(discarding_player: &'s str,
+    pub fn new(discarding_player: Seat,
+               discarded_slot: usize,
                discarded_card: &'s Card,
                drawn_card: Option<&'s Card>,
-               game_state: &'s GameState)
+               state: StateUpdate<'s>,
+               state_version: usize,
+               request_id: Option<&'s str>)
                -> Self {
         DiscardCardResponse {
             msg_type:          DiscardCardResponseType,
             discarding_player: discarding_player,
+            discarded_slot:    discarded_slot,
             discarded_card:    discarded_card,
             drawn_card:        drawn_card,
-            game_state:        game_state,
+            drawn_slot:        drawn_card.map(|_| DRAW_SLOT),
+            game_state:        state.full(),
+            state_delta:       state.delta(),
+            state_version:     state_version,
+            request_id:        request_id,
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct PlayCardResponse<'s> {
-    msg_type:       ResponseType,
-    playing_player: &'s str,
-    played_card:    &'s Card,
-    drawn_card:     Option<&'s Card>,
-    success:        bool,
-    game_state:     &'s GameState,
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct PlayCardResponse<'s> {
+        msg_type:       ResponseType = PlayCardResponseType,
+        playing_player: Seat,
+        played_slot:    usize,
+        played_card:    &'s Card,
+        drawn_card:     Option<&'s Card>,
+        drawn_slot:     Option<usize>,
+        success:        bool,
+        game_state:     Option<&'s GameState>,
+        state_delta:    Option<&'s StateDelta>,
+        state_version:  usize,
+        request_id:     Option<&'s str>,
+    }
 }
 
 impl<'s> PlayCardResponse<'s> {
-    pub fn new(playing_player: &'s str,
+    pub fn new(playing_player: Seat,
+               played_slot: usize,
                played_card: &'s Card,
                drawn_card: Option<&'s Card>,
                success: bool,
-               game_state: &'s GameState)
+               state: StateUpdate<'s>,
+               state_version: usize,
+               request_id: Option<&'s str>)
                -> Self {
         PlayCardResponse {
             msg_type:       PlayCardResponseType,
             playing_player: playing_player,
+            played_slot:    played_slot,
             played_card:    played_card,
             drawn_card:     drawn_card,
+            drawn_slot:     drawn_card.map(|_| DRAW_SLOT),
             success:        success,
-            game_state:     game_state,
+            game_state:     state.full(),
+            state_delta:    state.delta(),
+            state_version:  state_version,
+            request_id:     request_id,
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct HintColorResponse<'s> {
-    msg_type:       ResponseType,
-    hinting_player: &'s str,
-    target_player:  &'s str,
-    hinted_color:   &'s Color,
-    game_state:     &'s GameState,
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct HintColorResponse<'s> {
+        msg_type:       ResponseType = HintColorResposeType,
+        hinting_player: Seat,
+        target_player:  Seat,
+        hinted_color:   &'s Color,
+        game_state:     Option<&'s GameState>,
+        state_delta:    Option<&'s StateDelta>,
+        state_version:  usize,
+        request_id:     Option<&'s str>,
+    }
 }
 
 impl<'s> HintColorResponse<'s> {
-    pub fn new(hinting_player: &'s str,
-               target_player: &'s str,
+    pub fn new(hinting_player: Seat,
+               target_player: Seat,
                hinted_color: &'s Color,
-               game_state: &'s GameState)
+               state: StateUpdate<'s>,
+               state_version: usize,
+               request_id: Option<&'s str>)
                -> Self {
         HintColorResponse {
             msg_type:       HintColorResposeType,
             hinting_player: hinting_player,
             target_player:  target_player,
             hinted_color:   hinted_color,
-            game_state:     game_state,
+            game_state:     state.full(),
+            state_delta:    state.delta(),
+            state_version:  state_version,
+            request_id:     request_id,
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct HintNumberResponse<'s> {
-    msg_type:       ResponseType,
-    hinting_player: &'s str,
-    target_player:  &'s str,
-    hinted_number:  &'s Number,
-    game_state:     &'s GameState,
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct HintNumberResponse<'s> {
+        msg_type:       ResponseType = HintNumberResposeType,
+        hinting_player: Seat,
+        target_player:  Seat,
+        hinted_number:  &'s Number,
+        game_state:     Option<&'s GameState>,
+        state_delta:    Option<&'s StateDelta>,
+        state_version:  usize,
+        request_id:     Option<&'s str>,
+    }
 }
 
 impl<'s> HintNumberResponse<'s> {
-    pub fn new(hinting_player: &'s str,
-               target_player: &'s str,
+    pub fn new(hinting_player: Seat,
+               target_player: Seat,
                hinted_number: &'s Number,
-               game_state: &'s GameState)
+               state: StateUpdate<'s>,
+               state_version: usize,
+               request_id: Option<&'s str>)
                -> Self {
         HintNumberResponse {
             msg_type:       HintNumberResposeType,
             hinting_player: hinting_player,
             target_player:  target_player,
             hinted_number:  hinted_number,
-            game_state:     game_state,
+            game_state:     state.full(),
+            state_delta:    state.delta(),
+            state_version:  state_version,
+            request_id:     request_id,
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct GameOverResponse {
-    msg_type: ResponseType,
-    score:    usize
+protocol_struct! {
+    /// Once the game is over the notes of all players are revealed.
+    #[derive(RustcEncodable)]
+    pub struct GameOverResponse<'s> {
+        msg_type:   ResponseType = GameOverResponseType,
+        score:      usize,
+        reason:     Option<&'s str>,
+        notes:      Vec<CardNote>,
+        request_id: Option<&'s str>,
+    }
 }
 
-impl GameOverResponse {
-    pub fn new(score: usize) -> Self {
+impl<'s> GameOverResponse<'s> {
+    pub fn new(score: usize, reason: Option<&'s str>, notes: Vec<CardNote>, request_id: Option<&'s str>) -> Self {
         GameOverResponse {
-            msg_type: GameOverResponseType,
-            score:    score
+            msg_type:   GameOverResponseType,
+            score:      score,
+            reason:     reason,
+            notes:      notes,
+            request_id: request_id,
         }
     }
 }
 
-#[derive(RustcEncodable)]
-pub struct GameStartResponse<'s> {
-    msg_type: ResponseType,
-    game_state:  &'s GameState,
+protocol_struct! {
+    /// `notes` are the receiving player's own notes, which matters when they reconnect.
+    #[derive(RustcEncodable)]
+    pub struct GameStartResponse<'s> {
+        msg_type:      ResponseType = GameStartResponseType,
+        seat_order:    Vec<&'s str>,
+        game_state:    &'s GameState,
+        notes:         Vec<CardNote>,
+        state_version: usize,
+        request_id:    Option<&'s str>,
+    }
 }
 
 impl<'s> GameStartResponse<'s> {
-    pub fn new(game_state: &'s GameState) -> Self {
+    pub fn new(game_state: &'s GameState, notes: Vec<CardNote>, state_version: usize, request_id: Option<&'s str>) -> Self {
         GameStartResponse {
-            msg_type:    GameStartResponseType,
-            game_state:  game_state,
+            msg_type:      GameStartResponseType,
+            seat_order:    game_state.player_names(),
+            game_state:    game_state,
+            notes:         notes,
+            state_version: state_version,
+            request_id:    request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    /// The names of the players by seat.
+    #[derive(RustcEncodable)]
+    pub struct SeatOrderResponse<'s> {
+        msg_type:     ResponseType = SeatOrderResponseType,
+        seat_order:   Vec<&'s str>,
+        first_player: Seat,
+        request_id:   Option<&'s str>,
+    }
+}
+
+impl<'s> SeatOrderResponse<'s> {
+    pub fn new(game_state: &'s GameState, request_id: Option<&'s str>) -> Self {
+        SeatOrderResponse {
+            msg_type:     SeatOrderResponseType,
+            seat_order:   game_state.player_names(),
+            first_player: game_state.first_player(),
+            request_id:   request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct MoveCardResponse<'s> {
+        msg_type:      ResponseType = MoveCardResponseType,
+        moving_player: Seat,
+        card_id:       usize,
+        from_slot:     usize,
+        to_slot:       usize,
+        state_version: usize,
+        request_id:    Option<&'s str>,
+    }
+}
+
+impl<'s> MoveCardResponse<'s> {
+    pub fn new(moving_player: Seat,
+               card_id: usize,
+               from_slot: usize,
+               to_slot: usize,
+               state_version: usize,
+               request_id: Option<&'s str>)
+               -> Self {
+        MoveCardResponse {
+            msg_type:      MoveCardResponseType,
+            moving_player: moving_player,
+            card_id:       card_id,
+            from_slot:     from_slot,
+            to_slot:       to_slot,
+            state_version: state_version,
+            request_id:    request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    /// Only sent to the author of the note.
+    #[derive(RustcEncodable)]
+    pub struct NoteResponse<'s> {
+        msg_type:   ResponseType = NoteResponseType,
+        card_id:    usize,
+        text:       &'s str,
+        request_id: Option<&'s str>,
+    }
+}
+
+impl<'s> NoteResponse<'s> {
+    pub fn new(card_id: usize, text: &'s str, request_id: Option<&'s str>) -> Self {
+        NoteResponse {
+            msg_type:   NoteResponseType,
+            card_id:    card_id,
+            text:       text,
+            request_id: request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct SyncResponse<'s> {
+        msg_type:      ResponseType = SyncResponseType,
+        game_state:    &'s GameState,
+        notes:         Vec<CardNote>,
+        state_version: usize,
+        request_id:    Option<&'s str>,
+    }
+}
+
+impl<'s> SyncResponse<'s> {
+    pub fn new(game_state: &'s GameState, notes: Vec<CardNote>, state_version: usize, request_id: Option<&'s str>) -> Self {
+        SyncResponse {
+            msg_type:      SyncResponseType,
+            game_state:    game_state,
+            notes:         notes,
+            state_version: state_version,
+            request_id:    request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct LoginResponse<'s> {
+        msg_type:   ResponseType = LoginResponseType,
+        name:       &'s str,
+        token:      &'s str,
+        request_id: Option<&'s str>,
+    }
+}
+
+impl<'s> LoginResponse<'s> {
+    pub fn new(name: &'s str, token: &'s str, request_id: Option<&'s str>) -> Self {
+        LoginResponse {
+            msg_type:   LoginResponseType,
+            name:       name,
+            token:      token,
+            request_id: request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct StatsResponse<'s> {
+        msg_type:   ResponseType = StatsResponseType,
+        name:       &'s str,
+        stats:      &'s PlayerStats,
+        request_id: Option<&'s str>,
+    }
+}
+
+impl<'s> StatsResponse<'s> {
+    pub fn new(name: &'s str, stats: &'s PlayerStats, request_id: Option<&'s str>) -> Self {
+        StatsResponse {
+            msg_type:   StatsResponseType,
+            name:       name,
+            stats:      stats,
+            request_id: request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct HelloResponse<'s> {
+        msg_type:             ResponseType = HelloResponseType,
+        protocol_version:     usize,
+        min_protocol_version: usize,
+        max_protocol_version: usize,
+        capabilities:         &'s [Capability],
+        request_id:           Option<&'s str>,
+    }
+}
+
+impl<'s> HelloResponse<'s> {
+    pub fn new(protocol_version: usize, capabilities: &'s [Capability], request_id: Option<&'s str>) -> Self {
+        HelloResponse {
+            msg_type:             HelloResponseType,
+            protocol_version:     protocol_version,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
+            capabilities:         capabilities,
+            request_id:           request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct AdminResponse<'s> {
+        msg_type:   ResponseType = AdminResponseType,
+        command:    AdminCommand,
+        rooms:      Option<Vec<RoomInfo>>,
+        game_state: Option<&'s GameState>,
+        request_id: Option<&'s str>,
+    }
+}
+
+impl<'s> AdminResponse<'s> {
+    pub fn new(command: AdminCommand,
+               rooms: Option<Vec<RoomInfo>>,
+               game_state: Option<&'s GameState>,
+               request_id: Option<&'s str>)
+               -> Self {
+        AdminResponse {
+            msg_type:   AdminResponseType,
+            command:    command,
+            rooms:      rooms,
+            game_state: game_state,
+            request_id: request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct NoticeResponse<'s> {
+        msg_type:   ResponseType = NoticeResponseType,
+        message:    &'s str,
+        deadline:   Option<u64>,
+        request_id: Option<&'s str>,
+    }
+}
+
+impl<'s> NoticeResponse<'s> {
+    /// The deadline is the unix time in seconds at which the server is going to close all connections.
+    pub fn new(message: &'s str, deadline: Option<u64>, request_id: Option<&'s str>) -> Self {
+        NoticeResponse {
+            msg_type:   NoticeResponseType,
+            message:    message,
+            deadline:   deadline,
+            request_id: request_id,
+        }
+    }
+}
+
+protocol_struct! {
+    /// A card that may be played or discarded, with its position in the player's hand.
+    #[derive(RustcEncodable)]
+    pub struct LegalCard {
+        pub slot:    usize,
+        pub card_id: usize,
+    }
+}
+
+protocol_struct! {
+    /// A hint that may be given and the cards of the target it would touch.
+    #[derive(RustcEncodable)]
+    pub struct LegalHint {
+        pub target:           Seat,
+        pub color:            Option<Color>,
+        pub number:           Option<Number>,
+        pub touched_card_ids: Vec<usize>,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct LegalActionsResponse<'s> {
+        msg_type:   ResponseType = LegalActionsResponseType,
+        player:     Seat,
+        plays:      Vec<LegalCard>,
+        discards:   Vec<LegalCard>,
+        hints:      Vec<LegalHint>,
+        request_id: Option<&'s str>,
+    }
+}
+
+impl<'s> LegalActionsResponse<'s> {
+    /// All lists are empty when it is not the player's turn.
+    pub fn new(player: Seat,
+               plays: Vec<LegalCard>,
+               discards: Vec<LegalCard>,
+               hints: Vec<LegalHint>,
+               request_id: Option<&'s str>)
+               -> Self {
+        LegalActionsResponse {
+            msg_type:   LegalActionsResponseType,
+            player:     player,
+            plays:      plays,
+            discards:   discards,
+            hints:      hints,
+            request_id: request_id,
         }
     }
 }
 
 pub mod error_messages {
-    pub const MSG_TO_TXT_ERROR:              &'static str = "The received message could not be read as a String.";
+    use super::ErrorCode;
+    use super::ErrorCode::*;
+
+    pub const MSG_TO_TXT_ERROR:              &'static str = "The received message could not be read as text or MessagePack.";
     pub const UNABLE_TO_GET_MSG_TYPE:        &'static str = "The type of the message could not be read.";
     pub const UNABLE_TO_DESERIALIZE_PAYLOAD: &'static str = "The payload of the received message could not be deserialized.";
     pub const CATASTROPHIC_FUCKUP:           &'static str = "Catastrophic Fuckup! The server's done goofed.";
@@ -198,4 +607,64 @@ pub mod error_messages {
     pub const PLAYER_NOT_FOUND:              &'static str = "The given Player could not be found.";
     pub const GAME_IS_OVER:                  &'static str = "Tried to make a turn pass when no turns are left.";
     pub const NOT_YOUR_TURN:                 &'static str = "Tried to send a request when it was someone else's turn.";
+    pub const ACCOUNTS_DISABLED:             &'static str = "The server does not support player accounts.";
+    pub const ACCOUNT_ALREADY_EXISTS:        &'static str = "An account with the chosen name already exists.";
+    pub const ACCOUNT_NOT_SAVED:             &'static str = "The account could not be saved.";
+    pub const INVALID_CREDENTIALS:           &'static str = "The given name or password is wrong.";
+    pub const LOGIN_REQUIRED:                &'static str = "A login is required to join with the chosen name.";
+    pub const INVALID_SESSION:               &'static str = "The session token is invalid or belongs to another account.";
+    pub const STATS_DISABLED:                &'static str = "The server does not keep player statistics.";
+    pub const STATS_UNAVAILABLE:             &'static str = "The player statistics could not be read.";
+    pub const INCOMPATIBLE_PROTOCOL_VERSION: &'static str = "The client's protocol version is not supported by the server.";
+    pub const UNSUPPORTED_REQUEST:           &'static str = "The request is not part of the protocol version or capabilities negotiated by the client.";
+    pub const ADMIN_DISABLED:                &'static str = "The server does not accept admin requests.";
+    pub const INVALID_ADMIN_TOKEN:           &'static str = "The admin token is invalid.";
+    pub const SERVER_DRAINING:               &'static str = "The server is shutting down and does not accept new games.";
+    pub const GAME_NOT_STARTED:              &'static str = "The game has not been started yet.";
+    pub const HINT_TO_SELF:                  &'static str = "Players cannot give hints to themselves.";
+    pub const NOT_HOST:                      &'static str = "Only the host can arrange the seats.";
+    pub const INVALID_SEAT_ORDER:            &'static str = "The seat order must list every seat exactly once and start at one of them.";
+    pub const INVALID_SLOT:                  &'static str = "The given slot is not part of the Player's hand.";
+    pub const NOTE_TOO_LONG:                 &'static str = "A note may be at most 200 characters long.";
+    pub const PLAYER_KICKED:                 &'static str = "The Player was kicked from the game and cannot rejoin it.";
+
+    pub fn error_code(explanation: &'static str) -> ErrorCode {
+        match explanation {
+            MSG_TO_TXT_ERROR              => MsgToTxtError,
+            UNABLE_TO_GET_MSG_TYPE        => UnableToGetMsgType,
+            UNABLE_TO_DESERIALIZE_PAYLOAD => UnableToDeserializePayload,
+            PLAYER_ALREADY_EXISTS         => PlayerAlreadyExists,
+            ALREADY_CONNECTED             => AlreadyConnected,
+            CONN_GAME_ALREADY_STARTED     => ConnGameAlreadyStarted,
+            GAME_ALREADY_STARTED          => GameAlreadyStarted,
+            NOT_YET_CONNECTED             => NotYetConnected,
+            NO_CARDS                      => NoCards,
+            NO_HINT_TOKENS                => NoHintTokens,
+            CARD_NOT_FOUND                => CardNotFound,
+            PLAYER_NOT_FOUND              => PlayerNotFound,
+            GAME_IS_OVER                  => GameIsOver,
+            NOT_YOUR_TURN                 => NotYourTurn,
+            ACCOUNTS_DISABLED             => AccountsDisabled,
+            ACCOUNT_ALREADY_EXISTS        => AccountAlreadyExists,
+            ACCOUNT_NOT_SAVED             => AccountNotSaved,
+            INVALID_CREDENTIALS           => InvalidCredentials,
+            LOGIN_REQUIRED                => LoginRequired,
+            INVALID_SESSION               => InvalidSession,
+            STATS_DISABLED                => StatsDisabled,
+            STATS_UNAVAILABLE             => StatsUnavailable,
+            INCOMPATIBLE_PROTOCOL_VERSION => IncompatibleProtocolVersion,
+            UNSUPPORTED_REQUEST           => UnsupportedRequest,
+            ADMIN_DISABLED                => AdminDisabled,
+            INVALID_ADMIN_TOKEN           => InvalidAdminToken,
+            SERVER_DRAINING               => ServerDraining,
+            GAME_NOT_STARTED              => GameNotStarted,
+            HINT_TO_SELF                  => HintToSelf,
+            NOT_HOST                      => NotHost,
+            INVALID_SEAT_ORDER            => InvalidSeatOrder,
+            INVALID_SLOT                  => InvalidSlot,
+            NOTE_TOO_LONG                 => NoteTooLong,
+            PLAYER_KICKED                 => PlayerKicked,
+            _                             => InternalError,
+        }
+    }
 }
@@ -0,0 +1,877 @@
+use rustc_serialize::Encodable;
+use rustc_serialize::json::{Json, Object};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use cards::{Card, CardInHand, CardKnowledge, ClueRecord, Color, Number};
+use game_state::{FinishReason, GamePhase, GameState, Player};
+use delta::{CardLocation, CardMove, Place, StateDelta, TouchedCard};
+use stats::{PlayerResult, PlayerStats, VariantStats};
+use protocol::{Capability, SUPPORTED_CAPABILITIES};
+use monitoring::{RoomInfo, RoomStatus};
+use notes::CardNote;
+use requests::*;
+use responses::*;
+use serialization::{to_json, PublicGameState};
+
+const SCHEMA_DRAFT: &'static str = "http://json-schema.org/draft-04/schema#";
+
+/// Declares a struct of the protocol together with its `Definition`, so its schema is generated
+/// from the fields themselves and cannot drift apart from them. Messages declare the value of their
+/// `msg_type`, requests also get the `request_id` of their envelope.
+macro_rules! protocol_struct {
+    ($(#[$attr:meta])* pub struct $name:ident $(<$lt:lifetime>)* {
+        $mvis:vis msg_type: RequestType = $msg_type:expr,
+        $($(#[$fattr:meta])* $fvis:vis $field:ident: $fty:ty),* $(,)*
+    }) => {
+        $(#[$attr])*
+        pub struct $name $(<$lt>)* {
+            $mvis msg_type: RequestType,
+            $($(#[$fattr])* $fvis $field: $fty),*
+        }
+
+        impl<$($lt),*> $crate::schema::Schema for $name<$($lt),*> {
+            fn schema() -> $crate::rustc_serialize::json::Json { $crate::schema::reference::<Self>() }
+        }
+
+        impl<$($lt),*> $crate::schema::Definition for $name<$($lt),*> {
+            fn name() -> &'static str { stringify!($name) }
+
+            fn definition() -> $crate::rustc_serialize::json::Json {
+                $crate::schema::object(vec![
+                    $crate::schema::msg_type(&$msg_type),
+                    $($crate::schema::field::<$fty>(stringify!($field)),)*
+                    $crate::schema::optional("request_id", $crate::schema::string()),
+                ])
+            }
+        }
+    };
+    ($(#[$attr:meta])* pub struct $name:ident $(<$lt:lifetime>)* {
+        $mvis:vis msg_type: ResponseType = $msg_type:expr,
+        $($(#[$fattr:meta])* $fvis:vis $field:ident: $fty:ty),* $(,)*
+    }) => {
+        $(#[$attr])*
+        pub struct $name $(<$lt>)* {
+            $mvis msg_type: ResponseType,
+            $($(#[$fattr])* $fvis $field: $fty),*
+        }
+
+        impl<$($lt),*> $crate::schema::Schema for $name<$($lt),*> {
+            fn schema() -> $crate::rustc_serialize::json::Json { $crate::schema::reference::<Self>() }
+        }
+
+        impl<$($lt),*> $crate::schema::Definition for $name<$($lt),*> {
+            fn name() -> &'static str { stringify!($name) }
+
+            fn definition() -> $crate::rustc_serialize::json::Json {
+                $crate::schema::object(vec![
+                    $crate::schema::msg_type(&$msg_type),
+                    $($crate::schema::field::<$fty>(stringify!($field)),)*
+                ])
+            }
+        }
+    };
+    ($(#[$attr:meta])* pub struct $name:ident $(<$lt:lifetime>)* {
+        $($(#[$fattr:meta])* $fvis:vis $field:ident: $fty:ty),* $(,)*
+    }) => {
+        $(#[$attr])*
+        pub struct $name $(<$lt>)* {
+            $($(#[$fattr])* $fvis $field: $fty),*
+        }
+
+        impl<$($lt),*> $crate::schema::Schema for $name<$($lt),*> {
+            fn schema() -> $crate::rustc_serialize::json::Json { $crate::schema::reference::<Self>() }
+        }
+
+        impl<$($lt),*> $crate::schema::Definition for $name<$($lt),*> {
+            fn name() -> &'static str { stringify!($name) }
+
+            fn definition() -> $crate::rustc_serialize::json::Json {
+                $crate::schema::object(vec![
+                    $($crate::schema::field::<$fty>(stringify!($field)),)*
+                ])
+            }
+        }
+    };
+}
+
+/// A type that appears in the protocol and knows the JSON Schema of its serialized form.
+pub trait Schema {
+    fn schema() -> Json;
+
+    /// Whether a field of this type may be `null` and, in requests, may also be left out.
+    fn optional() -> bool { false }
+}
+
+/// A type with a definition of its own in the protocol schema, which other types refer to by name.
+/// Structs get theirs from `protocol_struct!`, the enumerations list the values of their `Encodable` impls.
+pub trait Definition {
+    fn name() -> &'static str;
+    fn definition() -> Json;
+}
+
+/// Types with a definition of their own are referred to by it.
+macro_rules! schema_by_reference {
+    ($($name:ident),*) => {
+        $(
+            impl Schema for $name {
+                fn schema() -> Json { reference::<$name>() }
+            }
+        )*
+    };
+}
+
+schema_by_reference!(Color, Number, FinishReason, GamePhase, GameState, Place, Capability, RequestType, ErrorCode, RoomStatus,
+                     AdminCommand);
+
+impl<'a, T: Schema + ?Sized> Schema for &'a T {
+    fn schema() -> Json { T::schema() }
+    fn optional() -> bool { T::optional() }
+}
+
+impl<T: Schema> Schema for Option<T> {
+    fn schema() -> Json { nullable(T::schema()) }
+    fn optional() -> bool { true }
+}
+
+impl<T: Schema> Schema for [T] {
+    fn schema() -> Json { array(T::schema()) }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> Json { array(T::schema()) }
+}
+
+impl<T: Schema> Schema for HashSet<T> {
+    fn schema() -> Json { array(T::schema()) }
+}
+
+/// Maps are serialized as json objects, so their keys are always strings.
+impl<K, V: Schema> Schema for HashMap<K, V> {
+    fn schema() -> Json { map(V::schema()) }
+}
+
+impl Schema for usize {
+    fn schema() -> Json { integer() }
+}
+
+impl Schema for u64 {
+    fn schema() -> Json { integer() }
+}
+
+impl Schema for f64 {
+    fn schema() -> Json { number() }
+}
+
+impl Schema for bool {
+    fn schema() -> Json { boolean() }
+}
+
+impl Schema for str {
+    fn schema() -> Json { string() }
+}
+
+impl Schema for String {
+    fn schema() -> Json { string() }
+}
+
+/// The JSON Schema of the whole protocol, every message is either a `Request` or a `Response`.
+pub fn protocol_schema() -> Json {
+    let mut defs = BTreeMap::new();
+
+    define::<Color>(&mut defs);
+    define::<Number>(&mut defs);
+    define::<Card>(&mut defs);
+    define::<ClueRecord>(&mut defs);
+    define::<CardKnowledge>(&mut defs);
+    define::<CardInHand>(&mut defs);
+    define::<Player>(&mut defs);
+    define::<FinishReason>(&mut defs);
+    define::<GamePhase>(&mut defs);
+    define::<GameState>(&mut defs);
+    define::<Place>(&mut defs);
+    define::<CardLocation>(&mut defs);
+    define::<CardMove>(&mut defs);
+    define::<TouchedCard>(&mut defs);
+    define::<StateDelta>(&mut defs);
+    define::<PlayerResult>(&mut defs);
+    define::<VariantStats>(&mut defs);
+    define::<PlayerStats>(&mut defs);
+    define::<Capability>(&mut defs);
+    define::<RequestType>(&mut defs);
+    define::<ErrorCode>(&mut defs);
+    define::<RoomStatus>(&mut defs);
+    define::<RoomInfo>(&mut defs);
+    define::<AdminCommand>(&mut defs);
+    define::<LegalCard>(&mut defs);
+    define::<LegalHint>(&mut defs);
+    define::<CardNote>(&mut defs);
+
+    let requests = vec![
+        define::<ConnectionRequest>(&mut defs),
+        define::<DiscardCardRequest>(&mut defs),
+        define::<HintColorRequest>(&mut defs),
+        define::<HintNumberRequest>(&mut defs),
+        define::<PlayCardRequest>(&mut defs),
+        define::<GameStartRequest>(&mut defs),
+        define::<RegisterRequest>(&mut defs),
+        define::<LoginRequest>(&mut defs),
+        define::<StatsRequest>(&mut defs),
+        define::<HelloRequest>(&mut defs),
+        define::<SyncRequest>(&mut defs),
+        define::<AdminRequest>(&mut defs),
+        define::<LegalActionsRequest>(&mut defs),
+        define::<SeatOrderRequest>(&mut defs),
+        define::<MoveCardRequest>(&mut defs),
+        define::<NoteRequest>(&mut defs),
+    ];
+    let responses = vec![
+        define::<ErrorResponse>(&mut defs),
+        define::<ConnectionResponse>(&mut defs),
+        define::<DiscardCardResponse>(&mut defs),
+        define::<PlayCardResponse>(&mut defs),
+        define::<HintColorResponse>(&mut defs),
+        define::<HintNumberResponse>(&mut defs),
+        define::<GameOverResponse>(&mut defs),
+        define::<GameStartResponse>(&mut defs),
+        define::<LoginResponse>(&mut defs),
+        define::<StatsResponse>(&mut defs),
+        define::<HelloResponse>(&mut defs),
+        define::<SyncResponse>(&mut defs),
+        define::<AdminResponse>(&mut defs),
+        define::<NoticeResponse>(&mut defs),
+        define::<LegalActionsResponse>(&mut defs),
+        define::<SeatOrderResponse>(&mut defs),
+        define::<MoveCardResponse>(&mut defs),
+        define::<NoteResponse>(&mut defs),
+    ];
+    defs.insert("Request".to_owned(), one_of(requests));
+    defs.insert("Response".to_owned(), one_of(responses));
+
+    let mut schema = BTreeMap::new();
+    schema.insert("$schema".to_owned(), Json::String(SCHEMA_DRAFT.to_owned()));
+    schema.insert("title".to_owned(), Json::String("Hanabi server protocol".to_owned()));
+    schema.insert("definitions".to_owned(), Json::Object(defs));
+    schema.insert("oneOf".to_owned(), Json::Array(vec![definition("Request"), definition("Response")]));
+    Json::Object(schema)
+}
+
+fn define<T: Definition>(defs: &mut Object) -> Json {
+    defs.insert(T::name().to_owned(), T::definition());
+    reference::<T>()
+}
+
+fn definition(name: &str) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("$ref".to_owned(), Json::String(format!("#/definitions/{}", name)));
+    Json::Object(obj)
+}
+
+pub fn reference<T: Definition>() -> Json {
+    definition(T::name())
+}
+
+fn of_type(name: &str) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("type".to_owned(), Json::String(name.to_owned()));
+    Json::Object(obj)
+}
+
+pub fn string() -> Json {
+    of_type("string")
+}
+
+fn integer() -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("type".to_owned(), Json::String("integer".to_owned()));
+    obj.insert("minimum".to_owned(), Json::U64(0));
+    Json::Object(obj)
+}
+
+fn number() -> Json {
+    of_type("number")
+}
+
+fn boolean() -> Json {
+    of_type("boolean")
+}
+
+fn array(items: Json) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("type".to_owned(), Json::String("array".to_owned()));
+    obj.insert("items".to_owned(), items);
+    Json::Object(obj)
+}
+
+/// A json object whose keys are not fixed, like the serialization of a `HashMap`.
+fn map(values: Json) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("type".to_owned(), Json::String("object".to_owned()));
+    obj.insert("additionalProperties".to_owned(), values);
+    Json::Object(obj)
+}
+
+fn nullable(schema: Json) -> Json {
+    one_of(vec![of_type("null"), schema])
+}
+
+fn one_of(schemas: Vec<Json>) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("oneOf".to_owned(), Json::Array(schemas));
+    Json::Object(obj)
+}
+
+fn enumeration<T: Encodable>(values: &[T]) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("type".to_owned(), Json::String("string".to_owned()));
+    obj.insert("enum".to_owned(), Json::Array(values.iter().map(to_json).collect()));
+    Json::Object(obj)
+}
+
+/// The `msg_type` field, which only permits the type of the message it belongs to.
+pub fn msg_type<T: Encodable>(value: &T) -> Field {
+    required("msg_type", enumeration(&[value]))
+}
+
+pub struct Field {
+    name:     &'static str,
+    schema:   Json,
+    required: bool,
+}
+
+/// A field described by its type, which is optional if the type is.
+pub fn field<T: Schema + ?Sized>(name: &'static str) -> Field {
+    Field {
+        name:     name,
+        schema:   T::schema(),
+        required: !T::optional(),
+    }
+}
+
+fn required(name: &'static str, schema: Json) -> Field {
+    Field {
+        name:     name,
+        schema:   schema,
+        required: true,
+    }
+}
+
+/// A field that may be `null` and, in requests, may also be left out.
+pub fn optional(name: &'static str, schema: Json) -> Field {
+    Field {
+        name:     name,
+        schema:   nullable(schema),
+        required: false,
+    }
+}
+
+pub fn object(fields: Vec<Field>) -> Json {
+    let required = fields.iter().filter(|f| f.required).map(|f| Json::String(f.name.to_owned())).collect();
+    let properties = fields.into_iter().map(|f| (f.name.to_owned(), f.schema)).collect();
+
+    let mut obj = BTreeMap::new();
+    obj.insert("type".to_owned(), Json::String("object".to_owned()));
+    obj.insert("properties".to_owned(), Json::Object(properties));
+    obj.insert("required".to_owned(), Json::Array(required));
+    obj.insert("additionalProperties".to_owned(), Json::Boolean(false));
+    Json::Object(obj)
+}
+
+impl Definition for Color {
+    fn name() -> &'static str { "Color" }
+
+    fn definition() -> Json {
+        enumeration(&[Color::Red, Color::Yellow, Color::Green, Color::Blue, Color::White])
+    }
+}
+
+impl Definition for Number {
+    fn name() -> &'static str { "Number" }
+
+    fn definition() -> Json {
+        enumeration(&[Number::One, Number::Two, Number::Three, Number::Four, Number::Five])
+    }
+}
+
+/// The game state is serialized without its seed, which is what `PublicGameState` holds.
+impl Definition for GameState {
+    fn name() -> &'static str { "GameState" }
+
+    fn definition() -> Json {
+        PublicGameState::definition()
+    }
+}
+
+impl Definition for FinishReason {
+    fn name() -> &'static str { "FinishReason" }
+
+    fn definition() -> Json {
+        enumeration(&[FinishReason::Strikes, FinishReason::FinalRoundOver, FinishReason::Aborted])
+    }
+}
+
+impl Definition for GamePhase {
+    fn name() -> &'static str { "GamePhase" }
+
+    fn definition() -> Json {
+        object(vec![
+            required("name",   enumeration(&["LOBBY", "DEALING", "IN_PROGRESS", "FINAL_ROUND", "FINISHED"])),
+            optional("reason", reference::<FinishReason>()),
+        ])
+    }
+}
+
+impl Definition for Place {
+    fn name() -> &'static str { "Place" }
+
+    fn definition() -> Json {
+        enumeration(&[Place::Deck, Place::Hand, Place::Played, Place::Discarded])
+    }
+}
+
+impl Definition for Capability {
+    fn name() -> &'static str { "Capability" }
+
+    fn definition() -> Json {
+        enumeration(SUPPORTED_CAPABILITIES)
+    }
+}
+
+impl Definition for RequestType {
+    fn name() -> &'static str { "RequestType" }
+
+    fn definition() -> Json {
+        enumeration(&[
+            RequestType::ConnectionRequestType,
+            RequestType::DiscardCardRequestType,
+            RequestType::HintColorRequestType,
+            RequestType::HintNumberRequestType,
+            RequestType::PlayCardRequestType,
+            RequestType::GameStartRequestType,
+            RequestType::RegisterRequestType,
+            RequestType::LoginRequestType,
+            RequestType::StatsRequestType,
+            RequestType::HelloRequestType,
+            RequestType::SyncRequestType,
+            RequestType::AdminRequestType,
+            RequestType::LegalActionsRequestType,
+            RequestType::SeatOrderRequestType,
+            RequestType::MoveCardRequestType,
+            RequestType::NoteRequestType,
+        ])
+    }
+}
+
+impl Definition for ErrorCode {
+    fn name() -> &'static str { "ErrorCode" }
+
+    fn definition() -> Json {
+        enumeration(&[
+            ErrorCode::MsgToTxtError,
+            ErrorCode::UnableToGetMsgType,
+            ErrorCode::UnableToDeserializePayload,
+            ErrorCode::InternalError,
+            ErrorCode::PlayerAlreadyExists,
+            ErrorCode::AlreadyConnected,
+            ErrorCode::ConnGameAlreadyStarted,
+            ErrorCode::GameAlreadyStarted,
+            ErrorCode::NotYetConnected,
+            ErrorCode::NoCards,
+            ErrorCode::NoHintTokens,
+            ErrorCode::CardNotFound,
+            ErrorCode::PlayerNotFound,
+            ErrorCode::GameIsOver,
+            ErrorCode::NotYourTurn,
+            ErrorCode::AccountsDisabled,
+            ErrorCode::AccountAlreadyExists,
+            ErrorCode::AccountNotSaved,
+            ErrorCode::InvalidCredentials,
+            ErrorCode::LoginRequired,
+            ErrorCode::InvalidSession,
+            ErrorCode::StatsDisabled,
+            ErrorCode::StatsUnavailable,
+            ErrorCode::IncompatibleProtocolVersion,
+            ErrorCode::UnsupportedRequest,
+            ErrorCode::AdminDisabled,
+            ErrorCode::InvalidAdminToken,
+            ErrorCode::ServerDraining,
+            ErrorCode::GameNotStarted,
+            ErrorCode::HintToSelf,
+            ErrorCode::NotHost,
+            ErrorCode::InvalidSeatOrder,
+            ErrorCode::InvalidSlot,
+            ErrorCode::NoteTooLong,
+            ErrorCode::PlayerKicked,
+        ])
+    }
+}
+
+impl Definition for RoomStatus {
+    fn name() -> &'static str { "RoomStatus" }
+
+    fn definition() -> Json {
+        enumeration(&[RoomStatus::Waiting, RoomStatus::Running, RoomStatus::Finished])
+    }
+}
+
+impl Definition for AdminCommand {
+    fn name() -> &'static str { "AdminCommand" }
+
+    fn definition() -> Json {
+        enumeration(&[
+            AdminCommand::ListRooms,
+            AdminCommand::Inspect,
+            AdminCommand::Kick,
+            AdminCommand::EndGame,
+            AdminCommand::Notice,
+            AdminCommand::Shutdown,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::{Decodable, Encodable};
+    use rustc_serialize::json::{self, Json};
+    use super::*;
+    use cards::{Color, Number};
+    use game_state::{Action, GameState, Outcome, DRAW_SLOT};
+    use delta::{Snapshot, StateDelta, StateUpdate};
+    use stats::{PlayerResult, PlayerStats, VariantStats};
+    use protocol::SUPPORTED_CAPABILITIES;
+    use monitoring::{RoomInfo, RoomStatus};
+    use responses::error_messages::*;
+    use msgpack;
+
+    /// Validates `value` against the subset of JSON Schema emitted by `protocol_schema`.
+    fn validate(schema: &Json, root: &Json, value: &Json, path: &str) -> Result<(), String> {
+        let obj = schema.as_object().unwrap();
+        if let Some(target) = obj.get("$ref").and_then(|r| r.as_string()) {
+            let name = target.rsplit('/').next().unwrap();
+            let def = try!(root.find_path(&["definitions", name]).ok_or(format!("{}: unknown definition {}", path, name)));
+            return validate(def, root, value, path);
+        }
+        if let Some(options) = obj.get("oneOf").and_then(|o| o.as_array()) {
+            let matching = options.iter().filter(|o| validate(o, root, value, path).is_ok()).count();
+            return match matching {
+                1 => Ok(()),
+                n => Err(format!("{}: {} instead of exactly one alternative match {}", path, n, value)),
+            };
+        }
+        if let Some(values) = obj.get("enum").and_then(|e| e.as_array()) {
+            if !values.contains(value) {
+                return Err(format!("{}: {} is not one of {:?}", path, value, values));
+            }
+        }
+        let type_matches = match obj.get("type").and_then(|t| t.as_string()) {
+            Some("null")    => value.is_null(),
+            Some("boolean") => value.is_boolean(),
+            Some("integer") => value.is_u64(),
+            Some("number")  => value.is_number(),
+            Some("string")  => value.is_string(),
+            Some("array")   => value.is_array(),
+            Some("object")  => value.is_object(),
+            _               => true,
+        };
+        if !type_matches {
+            return Err(format!("{}: {} is not of type {}", path, value, obj["type"]));
+        }
+        if let (Some(items), Some(values)) = (obj.get("items"), value.as_array()) {
+            for (i, item) in values.iter().enumerate() {
+                try!(validate(items, root, item, &format!("{}[{}]", path, i)));
+            }
+        }
+        if let Some(fields) = value.as_object() {
+            let properties = obj.get("properties").and_then(|p| p.as_object());
+            for name in obj.get("required").and_then(|r| r.as_array()).into_iter().flat_map(|r| r.iter()) {
+                let name = name.as_string().unwrap();
+                if !fields.contains_key(name) {
+                    return Err(format!("{}: missing field {}", path, name));
+                }
+            }
+            for (name, field) in fields {
+                let field_path = format!("{}.{}", path, name);
+                match (properties.and_then(|p| p.get(name)), obj.get("additionalProperties")) {
+                    (Some(property), _)                    => try!(validate(property, root, field, &field_path)),
+                    (None, Some(&Json::Boolean(false)))    => return Err(format!("{}: undeclared field", field_path)),
+                    (None, Some(additional))               => try!(validate(additional, root, field, &field_path)),
+                    (None, None)                           => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Also checks that the response comes out of MessagePack the same as it went in, as both
+    /// encodings share this schema.
+    fn assert_conforms<T: Encodable>(msg: &T) {
+        let schema = protocol_schema();
+        let value = to_json(msg);
+        if let Err(e) = validate(&schema, &schema, &value, "$") {
+            panic!("{} does not conform to the protocol schema: {}", value, e);
+        }
+        assert_eq!(msgpack::decode(&msgpack::encode(&value)).as_ref(), Ok(&value), "MessagePack changed {}", value);
+    }
+
+    fn assert_request_conforms<T: Decodable>(req: &str) {
+        let schema = protocol_schema();
+        let value = Json::from_str(req).unwrap();
+        if let Err(e) = validate(&schema, &schema, &value, "$") {
+            panic!("{} does not conform to the protocol schema: {}", value, e);
+        }
+        json::decode::<T>(req).unwrap();
+    }
+
+    /// Checks that `req` conforms to the schema and decodes into `T`, for the requests walked by
+    /// `every_request_type_is_defined`.
+    fn request<T: Decodable>(req: &str) -> Json {
+        assert_request_conforms::<T>(req);
+        Json::from_str(req).unwrap()
+    }
+
+    fn sample_request(req_type: RequestType) -> Json {
+        match req_type {
+            RequestType::ConnectionRequestType   => request::<ConnectionRequest>(r#"{"msg_type": "CONNECTION_REQUEST", "name": "alice"}"#),
+            RequestType::DiscardCardRequestType  => request::<DiscardCardRequest>(r#"{"msg_type": "DISCARD_CARD_REQUEST", "slot": 0}"#),
+            RequestType::HintColorRequestType    => request::<HintColorRequest>(r#"{"msg_type": "HINT_COLOR_REQUEST", "target_seat": 1, "color": "RED"}"#),
+            RequestType::HintNumberRequestType   => request::<HintNumberRequest>(r#"{"msg_type": "HINT_NUMBER_REQUEST", "target_seat": 1, "number": "ONE"}"#),
+            RequestType::PlayCardRequestType     => request::<PlayCardRequest>(r#"{"msg_type": "PLAY_CARD_REQUEST", "slot": 0}"#),
+            RequestType::GameStartRequestType    => request::<GameStartRequest>(r#"{"msg_type": "GAME_START_REQUEST"}"#),
+            RequestType::RegisterRequestType     => request::<RegisterRequest>(r#"{"msg_type": "REGISTER_REQUEST", "name": "alice", "password": "secret"}"#),
+            RequestType::LoginRequestType        => request::<LoginRequest>(r#"{"msg_type": "LOGIN_REQUEST", "name": "alice", "password": "secret"}"#),
+            RequestType::StatsRequestType        => request::<StatsRequest>(r#"{"msg_type": "STATS_REQUEST", "name": "bob"}"#),
+            RequestType::HelloRequestType        => request::<HelloRequest>(r#"{"msg_type": "HELLO_REQUEST", "protocol_version": 3, "capabilities": []}"#),
+            RequestType::SyncRequestType         => request::<SyncRequest>(r#"{"msg_type": "SYNC_REQUEST"}"#),
+            RequestType::AdminRequestType        => request::<AdminRequest>(r#"{"msg_type": "ADMIN_REQUEST", "admin_token": "secret", "command": "NOTICE", "message": "Hi."}"#),
+            RequestType::LegalActionsRequestType => request::<LegalActionsRequest>(r#"{"msg_type": "LEGAL_ACTIONS_REQUEST"}"#),
+            RequestType::SeatOrderRequestType    => request::<SeatOrderRequest>(r#"{"msg_type": "SEAT_ORDER_REQUEST", "seats": [0, 1]}"#),
+            RequestType::MoveCardRequestType     => request::<MoveCardRequest>(r#"{"msg_type": "MOVE_CARD_REQUEST", "card_id": 3, "to_slot": 1}"#),
+            RequestType::NoteRequestType         => request::<NoteRequest>(r#"{"msg_type": "NOTE_REQUEST", "card_id": 3, "text": "r5"}"#),
+        }
+    }
+
+    const RESPONSE_TYPES: &'static [ResponseType] = &[
+        ResponseType::ErrorResponseType,
+        ResponseType::ConnectionResponseType,
+        ResponseType::DiscardCardResponseType,
+        ResponseType::PlayCardResponseType,
+        ResponseType::HintColorResposeType,
+        ResponseType::HintNumberResposeType,
+        ResponseType::GameOverResponseType,
+        ResponseType::GameStartResponseType,
+        ResponseType::LoginResponseType,
+        ResponseType::StatsResponseType,
+        ResponseType::HelloResponseType,
+        ResponseType::SyncResponseType,
+        ResponseType::AdminResponseType,
+        ResponseType::NoticeResponseType,
+        ResponseType::LegalActionsResponseType,
+        ResponseType::SeatOrderResponseType,
+        ResponseType::MoveCardResponseType,
+        ResponseType::NoteResponseType,
+    ];
+
+    fn sample_response(resp_type: &ResponseType, game_state: &GameState, stats: &PlayerStats) -> Json {
+        let card = &game_state.players()[0].cards[0].card;
+        let full = StateUpdate::Full(game_state);
+        match *resp_type {
+            ResponseType::ErrorResponseType        => to_json(&ErrorResponse::new(NOT_YOUR_TURN, None, None, None)),
+            ResponseType::ConnectionResponseType   => to_json(&ConnectionResponse::new(vec!["alice"], None)),
+            ResponseType::DiscardCardResponseType  => to_json(&DiscardCardResponse::new(0, 0, card, Some(card), full, 1, None)),
+            ResponseType::PlayCardResponseType     => to_json(&PlayCardResponse::new(0, 0, card, None, true, full, 1, None)),
+            ResponseType::HintColorResposeType     => to_json(&HintColorResponse::new(0, 1, &Color::Red, full, 1, None)),
+            ResponseType::HintNumberResposeType    => to_json(&HintNumberResponse::new(0, 1, &Number::One, full, 1, None)),
+            ResponseType::GameOverResponseType     => to_json(&GameOverResponse::new(0, None, Vec::new(), None)),
+            ResponseType::GameStartResponseType    => to_json(&GameStartResponse::new(game_state, Vec::new(), 0, None)),
+            ResponseType::LoginResponseType        => to_json(&LoginResponse::new("alice", "token", None)),
+            ResponseType::StatsResponseType        => to_json(&StatsResponse::new("alice", stats, None)),
+            ResponseType::HelloResponseType        => to_json(&HelloResponse::new(3, SUPPORTED_CAPABILITIES, None)),
+            ResponseType::SyncResponseType         => to_json(&SyncResponse::new(game_state, Vec::new(), 0, None)),
+            ResponseType::AdminResponseType        => to_json(&AdminResponse::new(AdminCommand::Notice, None, None, None)),
+            ResponseType::NoticeResponseType       => to_json(&NoticeResponse::new("Hi.", None, None)),
+            ResponseType::LegalActionsResponseType => to_json(&LegalActionsResponse::new(1, vec![], vec![], vec![], None)),
+            ResponseType::SeatOrderResponseType    => to_json(&SeatOrderResponse::new(game_state, None)),
+            ResponseType::MoveCardResponseType     => to_json(&MoveCardResponse::new(0, card.id, 0, 1, 1, None)),
+            ResponseType::NoteResponseType         => to_json(&NoteResponse::new(card.id, "r5", None)),
+        }
+    }
+
+    /// The `msg_type` values permitted by the definitions listed in the `Request` or `Response` definition.
+    fn message_types(schema: &Json, message: &str) -> Vec<Json> {
+        let mut types: Vec<Json> = schema.find_path(&["definitions", message, "oneOf"]).unwrap().as_array().unwrap().iter().map(|r| {
+            let name = r["$ref"].as_string().unwrap().rsplit('/').next().unwrap();
+            schema.find_path(&["definitions", name, "properties", "msg_type", "enum"]).unwrap()[0].clone()
+        }).collect();
+        types.sort_by_key(|t| t.to_string());
+        types
+    }
+
+    fn started_game() -> GameState {
+        let mut game_state = GameState::default();
+        game_state.add_player("alice").unwrap();
+        game_state.add_player("bob").unwrap();
+        game_state.start().unwrap();
+        game_state
+    }
+
+    #[test]
+    fn requests_conform_to_schema() {
+        assert_request_conforms::<ConnectionRequest>(r#"{"msg_type": "CONNECTION_REQUEST", "name": "alice"}"#);
+        assert_request_conforms::<ConnectionRequest>(r#"{"msg_type": "CONNECTION_REQUEST", "name": "alice", "token": "abc", "request_id": "1"}"#);
+        assert_request_conforms::<DiscardCardRequest>(r#"{"msg_type": "DISCARD_CARD_REQUEST", "discarded_card_id": 3}"#);
+        assert_request_conforms::<DiscardCardRequest>(r#"{"msg_type": "DISCARD_CARD_REQUEST", "slot": 0}"#);
+        assert_request_conforms::<HintColorRequest>(r#"{"msg_type": "HINT_COLOR_REQUEST", "target_seat": 1, "color": "WHITE"}"#);
+        assert_request_conforms::<HintColorRequest>(r#"{"msg_type": "HINT_COLOR_REQUEST", "target_player": "bob", "color": "WHITE"}"#);
+        assert_request_conforms::<HintNumberRequest>(r#"{"msg_type": "HINT_NUMBER_REQUEST", "target_seat": 1, "number": "FIVE"}"#);
+        assert_request_conforms::<HintNumberRequest>(r#"{"msg_type": "HINT_NUMBER_REQUEST", "target_player": "bob", "number": "FIVE"}"#);
+        assert_request_conforms::<PlayCardRequest>(r#"{"msg_type": "PLAY_CARD_REQUEST", "played_card_id": 7, "request_id": null}"#);
+        assert_request_conforms::<PlayCardRequest>(r#"{"msg_type": "PLAY_CARD_REQUEST", "slot": 3, "request_id": "3"}"#);
+        assert_request_conforms::<GameStartRequest>(r#"{"msg_type": "GAME_START_REQUEST"}"#);
+        assert_request_conforms::<RegisterRequest>(r#"{"msg_type": "REGISTER_REQUEST", "name": "alice", "password": "secret"}"#);
+        assert_request_conforms::<LoginRequest>(r#"{"msg_type": "LOGIN_REQUEST", "name": "alice", "password": "secret"}"#);
+        assert_request_conforms::<StatsRequest>(r#"{"msg_type": "STATS_REQUEST"}"#);
+        assert_request_conforms::<HelloRequest>(r#"{"msg_type": "HELLO_REQUEST", "protocol_version": 2, "capabilities": ["DELTAS"]}"#);
+        assert_request_conforms::<SyncRequest>(r#"{"msg_type": "SYNC_REQUEST", "request_id": "sync"}"#);
+        assert_request_conforms::<AdminRequest>(r#"{"msg_type": "ADMIN_REQUEST", "admin_token": "secret", "command": "LIST_ROOMS"}"#);
+        assert_request_conforms::<AdminRequest>(r#"{"msg_type": "ADMIN_REQUEST", "admin_token": "secret", "command": "KICK", "player": "bob", "message": "Bye."}"#);
+        assert_request_conforms::<LegalActionsRequest>(r#"{"msg_type": "LEGAL_ACTIONS_REQUEST"}"#);
+        assert_request_conforms::<SeatOrderRequest>(r#"{"msg_type": "SEAT_ORDER_REQUEST", "seats": [1, 0]}"#);
+        assert_request_conforms::<SeatOrderRequest>(r#"{"msg_type": "SEAT_ORDER_REQUEST", "seats": [1, 0], "first_player": 1}"#);
+        assert_request_conforms::<MoveCardRequest>(r#"{"msg_type": "MOVE_CARD_REQUEST", "card_id": 12, "to_slot": 0}"#);
+        assert_request_conforms::<MoveCardRequest>(r#"{"msg_type": "MOVE_CARD_REQUEST", "slot": 4, "to_slot": 1, "request_id": "m"}"#);
+        assert_request_conforms::<NoteRequest>(r#"{"msg_type": "NOTE_REQUEST", "card_id": 12, "text": "chop"}"#);
+        assert_request_conforms::<NoteRequest>(r#"{"msg_type": "NOTE_REQUEST", "card_id": 12, "text": "", "request_id": "n"}"#);
+    }
+
+    #[test]
+    fn every_request_type_is_defined() {
+        let schema = protocol_schema();
+        let mut names = schema.find_path(&["definitions", "RequestType", "enum"]).unwrap().as_array().unwrap().clone();
+        names.sort_by_key(|n| n.to_string());
+        assert_eq!(message_types(&schema, "Request"), names);
+        for name in &names {
+            let req_type = json::decode::<RequestType>(&name.to_string()).unwrap();
+            assert_eq!(sample_request(req_type)["msg_type"], to_json(&req_type));
+        }
+    }
+
+    #[test]
+    fn every_response_type_is_defined() {
+        let schema = protocol_schema();
+        let game_state = started_game();
+        let stats = PlayerStats { games_played: 0, best_score: 0, variants: Vec::new(), recent_games: Vec::new() };
+        let mut types: Vec<Json> = RESPONSE_TYPES.iter().map(to_json).collect();
+        types.sort_by_key(|t| t.to_string());
+        assert_eq!(message_types(&schema, "Response"), types);
+        for resp_type in RESPONSE_TYPES {
+            let resp = sample_response(resp_type, &game_state, &stats);
+            assert_eq!(resp["msg_type"], to_json(resp_type));
+            if let Err(e) = validate(&schema, &schema, &resp, "$") {
+                panic!("{} does not conform to the protocol schema: {}", resp, e);
+            }
+        }
+    }
+
+    #[test]
+    fn schema_rejects_malformed_requests() {
+        let schema = protocol_schema();
+        for req in &[r#"{"msg_type": "DISCARD_CARD_REQUEST", "slot": "left"}"#,
+                     r#"{"msg_type": "HINT_COLOR_REQUEST", "target_player": "bob", "color": "PURPLE"}"#,
+                     r#"{"msg_type": "HINT_NUMBER_REQUEST", "target_seat": "bob", "number": "ONE"}"#,
+                     r#"{"msg_type": "GAME_START_REQUEST", "unknown": 1}"#,
+                     r#"{"msg_type": "NO_SUCH_REQUEST"}"#] {
+            assert!(validate(&schema, &schema, &Json::from_str(req).unwrap(), "$").is_err(), "{} was accepted", req);
+        }
+    }
+
+    #[test]
+    fn responses_conform_to_schema() {
+        let mut game_state = started_game();
+        let card_id = game_state.players()[0].cards[0].card.id;
+        let before = Snapshot::of(&game_state);
+        let (discarded, drawn) = match game_state.apply(&Action::Discard { player: 0, card_id: card_id }) {
+            Ok(Outcome::Discarded { discarded_card, drawn_card, .. }) => (discarded_card, drawn_card),
+            _                                                     => panic!("Discarding failed."),
+        };
+        game_state.apply(&Action::HintColor { player: 1, target: 0, color: Color::Red }).ok().unwrap();
+        let delta = StateDelta::between(&before, &game_state);
+        let full = StateUpdate::Full(&game_state);
+        let partial = StateUpdate::Delta(&delta);
+
+        assert_conforms(&ErrorResponse::new(NOT_YOUR_TURN, None, None, None));
+        assert_conforms(&ErrorResponse::new(CARD_NOT_FOUND, Some("details"), Some(RequestType::PlayCardRequestType), Some("1")));
+        assert_conforms(&ErrorResponse::new(CATASTROPHIC_FUCKUP, None, None, None));
+        assert_conforms(&ConnectionResponse::new(vec!["alice", "bob"], Some("1")));
+        assert_conforms(&DiscardCardResponse::new(0, 0, &discarded, drawn.as_ref(), full, 1, None));
+        assert_conforms(&DiscardCardResponse::new(0, 4, &discarded, None, partial, 1, None));
+        assert_conforms(&PlayCardResponse::new(0, 2, &discarded, drawn.as_ref(), false, full, 2, Some("2")));
+        assert_conforms(&PlayCardResponse::new(0, 1, &discarded, drawn.as_ref(), true, partial, 2, None));
+        assert_conforms(&HintColorResponse::new(0, 1, &Color::Red, full, 3, None));
+        assert_conforms(&HintNumberResponse::new(0, 1, &Number::Four, partial, 3, None));
+        let note = CardNote { author: 1, card_id: card_id, text: "probably r3".to_owned() };
+        assert_conforms(&GameOverResponse::new(12, None, Vec::new(), None));
+        assert_conforms(&GameOverResponse::new(7, Some("Ended by an administrator."), vec![note.clone()], Some("end")));
+        assert_conforms(&GameStartResponse::new(&game_state, Vec::new(), 0, None));
+        assert_conforms(&SyncResponse::new(&game_state, vec![note], 3, Some("sync")));
+        assert_conforms(&NoteResponse::new(card_id, "chop", Some("n")));
+        assert_conforms(&LoginResponse::new("alice", "token", None));
+        assert_conforms(&HelloResponse::new(2, SUPPORTED_CAPABILITIES, None));
+        assert_conforms(&NoticeResponse::new("Maintenance in five minutes.", None, None));
+        assert_conforms(&NoticeResponse::new("The server is shutting down.", Some(1500000000), None));
+        assert_conforms(&SeatOrderResponse::new(&game_state, Some("seats")));
+        assert_conforms(&MoveCardResponse::new(1, card_id, 4, 0, 3, None));
+
+        let room = RoomInfo {
+            room_id:       "game".to_owned(),
+            status:        RoomStatus::Running,
+            players:       vec!["alice".to_owned(), "bob".to_owned()],
+            connections:   2,
+            state_version: 3,
+            score:         game_state.score(),
+        };
+        assert_conforms(&AdminResponse::new(AdminCommand::ListRooms, Some(vec![room]), None, Some("rooms")));
+        assert_conforms(&AdminResponse::new(AdminCommand::Inspect, None, Some(&game_state), None));
+        assert_conforms(&AdminResponse::new(AdminCommand::Kick, None, None, None));
+
+        let plays = vec![LegalCard { slot: 0, card_id: card_id }];
+        let discards = vec![LegalCard { slot: 0, card_id: card_id }];
+        let hints = vec![
+            LegalHint { target: 1, color: Some(Color::Red), number: None, touched_card_ids: vec![card_id] },
+            LegalHint { target: 1, color: None, number: Some(Number::One), touched_card_ids: vec![] },
+        ];
+        assert_conforms(&LegalActionsResponse::new(0, plays, discards, hints, Some("legal")));
+        assert_conforms(&LegalActionsResponse::new(1, vec![], vec![], vec![], None));
+
+        let result = PlayerResult {
+            name:        "alice".to_owned(),
+            game_id:     "game".to_owned(),
+            score:       12,
+            players:     2,
+            variant:     game_state.variant().to_owned(),
+            strikes:     1,
+            clues_given: 4,
+            misplays:    1,
+        };
+        let stats = PlayerStats {
+            games_played: 1,
+            best_score:   12,
+            variants:     vec![VariantStats {
+                variant:       game_state.variant().to_owned(),
+                games_played:  1,
+                average_score: 12.0,
+                best_score:    12,
+            }],
+            recent_games: vec![result],
+        };
+        assert_conforms(&StatsResponse::new("alice", &stats, None));
+    }
+
+    #[test]
+    fn drawn_cards_come_with_their_slot() {
+        let game_state = started_game();
+        let card = game_state.players()[0].cards[0].card;
+        let full = StateUpdate::Full(&game_state);
+        let drawn = to_json(&DiscardCardResponse::new(0, 3, &card, Some(&card), full, 1, None));
+        assert_eq!(drawn.find("drawn_slot"), Some(&Json::U64(DRAW_SLOT as u64)));
+        let none_drawn = to_json(&PlayCardResponse::new(0, 3, &card, None, true, full, 1, None));
+        assert_eq!(none_drawn.find("drawn_slot"), Some(&Json::Null));
+    }
+}
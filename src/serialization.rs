@@ -1,21 +1,42 @@
-use rustc_serialize::{Encodable, Encoder, Decodable, Decoder};
-use responses::ResponseType;
+use std::collections::HashMap;
+use rustc_serialize::{json, Encodable, Encoder, Decodable, Decoder};
+use rustc_serialize::json::Json;
+use responses::{ResponseType, ErrorCode};
 use responses::ResponseType::*;
-use requests::RequestType;
-use cards::{Color, Number};
-use game_state::Void;
+use requests::{AdminCommand, RequestType};
+use cards::{Card, Color, Number};
+use protocol::Capability;
+use delta::Place;
+use monitoring::RoomStatus;
+use game_state::{FinishReason, GamePhase, GameState, Player, Seat, Void};
+use responses::error_messages::CATASTROPHIC_FUCKUP;
+
+/// Encodes a value into a json tree, e.g. to serialize it in another wire format or to embed it in other json.
+pub fn to_json<T: Encodable>(value: &T) -> Json {
+    Json::from_str(&json::encode(value).expect(CATASTROPHIC_FUCKUP)).expect(CATASTROPHIC_FUCKUP)
+}
 
 impl Encodable for ResponseType {
     fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
         let (name, index) = match *self {
-            ErrorResponseType       => ("ERROR_RESPONSE",        0),
-            ConnectionResponseType  => ("CONNECTION_RESPONSE",   1),
-            DiscardCardResponseType => ("DISCARD_CARD_RESPONSE", 2),
-            PlayCardResponseType    => ("PLAY_CARD_RESPONSE",    3),
-            HintColorResposeType    => ("HINT_COLOR_RESPONSE",   4),
-            HintNumberResposeType   => ("HINT_NUMBER_RESPONSE",  5),
-            GameOverResponseType    => ("GAME_OVER_RESPONSE",    6),
-            GameStartResponseType   => ("GAME_START_RESPONSE",   7)
+            ErrorResponseType        => ("ERROR_RESPONSE",         0),
+            ConnectionResponseType   => ("CONNECTION_RESPONSE",    1),
+            DiscardCardResponseType  => ("DISCARD_CARD_RESPONSE",  2),
+            PlayCardResponseType     => ("PLAY_CARD_RESPONSE",     3),
+            HintColorResposeType     => ("HINT_COLOR_RESPONSE",    4),
+            HintNumberResposeType    => ("HINT_NUMBER_RESPONSE",   5),
+            GameOverResponseType     => ("GAME_OVER_RESPONSE",     6),
+            GameStartResponseType    => ("GAME_START_RESPONSE",    7),
+            LoginResponseType        => ("LOGIN_RESPONSE",         8),
+            StatsResponseType        => ("STATS_RESPONSE",         9),
+            HelloResponseType        => ("HELLO_RESPONSE",         10),
+            SyncResponseType         => ("SYNC_RESPONSE",          11),
+            AdminResponseType        => ("ADMIN_RESPONSE",         12),
+            NoticeResponseType       => ("NOTICE_RESPONSE",        13),
+            LegalActionsResponseType => ("LEGAL_ACTIONS_RESPONSE", 14),
+            SeatOrderResponseType    => ("SEAT_ORDER_RESPONSE",    15),
+            MoveCardResponseType     => ("MOVE_CARD_RESPONSE",     16),
+            NoteResponseType         => ("NOTE_RESPONSE",          17),
         };
         enc.emit_enum("ResponseType", |enc| {
             enc.emit_enum_variant(name, index, 0, |_| {
@@ -25,6 +46,81 @@ impl Encodable for ResponseType {
     }
 }
 
+impl Encodable for RequestType {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, index) = match *self {
+            RequestType::ConnectionRequestType   => ("CONNECTION_REQUEST",    0),
+            RequestType::DiscardCardRequestType  => ("DISCARD_CARD_REQUEST",  1),
+            RequestType::HintColorRequestType    => ("HINT_COLOR_REQUEST",    2),
+            RequestType::HintNumberRequestType   => ("HINT_NUMBER_REQUEST",   3),
+            RequestType::PlayCardRequestType     => ("PLAY_CARD_REQUEST",     4),
+            RequestType::GameStartRequestType    => ("GAME_START_REQUEST",    5),
+            RequestType::RegisterRequestType     => ("REGISTER_REQUEST",      6),
+            RequestType::LoginRequestType        => ("LOGIN_REQUEST",         7),
+            RequestType::StatsRequestType        => ("STATS_REQUEST",         8),
+            RequestType::HelloRequestType        => ("HELLO_REQUEST",         9),
+            RequestType::SyncRequestType         => ("SYNC_REQUEST",          10),
+            RequestType::AdminRequestType        => ("ADMIN_REQUEST",         11),
+            RequestType::LegalActionsRequestType => ("LEGAL_ACTIONS_REQUEST", 12),
+            RequestType::SeatOrderRequestType    => ("SEAT_ORDER_REQUEST",    13),
+            RequestType::MoveCardRequestType     => ("MOVE_CARD_REQUEST",     14),
+            RequestType::NoteRequestType         => ("NOTE_REQUEST",          15),
+        };
+        enc.emit_enum("RequestType", |enc| {
+            enc.emit_enum_variant(name, index, 0, |_| {
+                Ok(())
+            })
+        })
+    }
+}
+
+impl Encodable for ErrorCode {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, index) = match *self {
+            ErrorCode::MsgToTxtError               => ("MSG_TO_TXT_ERROR",              0),
+            ErrorCode::UnableToGetMsgType          => ("UNABLE_TO_GET_MSG_TYPE",        1),
+            ErrorCode::UnableToDeserializePayload  => ("UNABLE_TO_DESERIALIZE_PAYLOAD", 2),
+            ErrorCode::InternalError               => ("INTERNAL_ERROR",                3),
+            ErrorCode::PlayerAlreadyExists         => ("PLAYER_ALREADY_EXISTS",         4),
+            ErrorCode::AlreadyConnected            => ("ALREADY_CONNECTED",             5),
+            ErrorCode::ConnGameAlreadyStarted      => ("CONN_GAME_ALREADY_STARTED",     6),
+            ErrorCode::GameAlreadyStarted          => ("GAME_ALREADY_STARTED",          7),
+            ErrorCode::NotYetConnected             => ("NOT_YET_CONNECTED",             8),
+            ErrorCode::NoCards                     => ("NO_CARDS",                      9),
+            ErrorCode::NoHintTokens                => ("NO_HINT_TOKENS",                10),
+            ErrorCode::CardNotFound                => ("CARD_NOT_FOUND",                11),
+            ErrorCode::PlayerNotFound              => ("PLAYER_NOT_FOUND",              12),
+            ErrorCode::GameIsOver                  => ("GAME_IS_OVER",                  13),
+            ErrorCode::NotYourTurn                 => ("NOT_YOUR_TURN",                 14),
+            ErrorCode::AccountsDisabled            => ("ACCOUNTS_DISABLED",             15),
+            ErrorCode::AccountAlreadyExists        => ("ACCOUNT_ALREADY_EXISTS",        16),
+            ErrorCode::AccountNotSaved             => ("ACCOUNT_NOT_SAVED",             17),
+            ErrorCode::InvalidCredentials          => ("INVALID_CREDENTIALS",           18),
+            ErrorCode::LoginRequired               => ("LOGIN_REQUIRED",                19),
+            ErrorCode::InvalidSession              => ("INVALID_SESSION",               20),
+            ErrorCode::StatsDisabled               => ("STATS_DISABLED",                21),
+            ErrorCode::StatsUnavailable            => ("STATS_UNAVAILABLE",             22),
+            ErrorCode::IncompatibleProtocolVersion => ("INCOMPATIBLE_PROTOCOL_VERSION", 23),
+            ErrorCode::UnsupportedRequest          => ("UNSUPPORTED_REQUEST",           24),
+            ErrorCode::AdminDisabled               => ("ADMIN_DISABLED",                25),
+            ErrorCode::InvalidAdminToken           => ("INVALID_ADMIN_TOKEN",           26),
+            ErrorCode::ServerDraining              => ("SERVER_DRAINING",               27),
+            ErrorCode::GameNotStarted              => ("GAME_NOT_STARTED",              28),
+            ErrorCode::HintToSelf                  => ("HINT_TO_SELF",                  29),
+            ErrorCode::NotHost                     => ("NOT_HOST",                      30),
+            ErrorCode::InvalidSeatOrder            => ("INVALID_SEAT_ORDER",            31),
+            ErrorCode::InvalidSlot                 => ("INVALID_SLOT",                  32),
+            ErrorCode::NoteTooLong                 => ("NOTE_TOO_LONG",                 33),
+            ErrorCode::PlayerKicked                => ("PLAYER_KICKED",                 34),
+        };
+        enc.emit_enum("ErrorCode", |enc| {
+            enc.emit_enum_variant(name, index, 0, |_| {
+                Ok(())
+            })
+        })
+    }
+}
+
 impl Encodable for Color {
     fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
         let (name, index) = match *self {
@@ -59,6 +155,43 @@ impl Encodable for Number {
     }
 }
 
+impl Encodable for Place {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, index) = match *self {
+            Place::Deck      => ("DECK",      0),
+            Place::Hand      => ("HAND",      1),
+            Place::Played    => ("PLAYED",    2),
+            Place::Discarded => ("DISCARDED", 3),
+        };
+        enc.emit_enum("Place", |enc| {
+            enc.emit_enum_variant(name, index, 0, |_| {
+                Ok(())
+            })
+        })
+    }
+}
+
+impl Encodable for RoomStatus {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, index) = match *self {
+            RoomStatus::Waiting  => ("WAITING",  0),
+            RoomStatus::Running  => ("RUNNING",  1),
+            RoomStatus::Finished => ("FINISHED", 2),
+        };
+        enc.emit_enum("RoomStatus", |enc| {
+            enc.emit_enum_variant(name, index, 0, |_| {
+                Ok(())
+            })
+        })
+    }
+}
+
+impl Encodable for Capability {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        enc.emit_str(self.name())
+    }
+}
+
 impl Decodable for Number {
     fn decode<D: Decoder>(d: &mut D) -> Result<Number, D::Error> {
         d.read_enum("Number", |d|  {
@@ -111,18 +244,155 @@ impl Decodable for RequestType {
                           "HINT_COLOR_REQUEST",
                           "HINT_NUMBER_REQUEST",
                           "PLAY_CARD_REQUEST",
-                          "GAME_START_REQUEST"];
+                          "GAME_START_REQUEST",
+                          "REGISTER_REQUEST",
+                          "LOGIN_REQUEST",
+                          "STATS_REQUEST",
+                          "HELLO_REQUEST",
+                          "SYNC_REQUEST",
+                          "ADMIN_REQUEST",
+                          "LEGAL_ACTIONS_REQUEST",
+                          "SEAT_ORDER_REQUEST",
+                          "MOVE_CARD_REQUEST",
+                          "NOTE_REQUEST"];
             d.read_enum_variant(names, |_, i| {
                 match i {
-                    0 => Ok(RequestType::ConnectionRequestType),
-                    1 => Ok(RequestType::DiscardCardRequestType),
-                    2 => Ok(RequestType::HintColorRequestType),
-                    3 => Ok(RequestType::HintNumberRequestType),
-                    4 => Ok(RequestType::PlayCardRequestType),
-                    5 => Ok(RequestType::GameStartRequestType),
+                    0  => Ok(RequestType::ConnectionRequestType),
+                    1  => Ok(RequestType::DiscardCardRequestType),
+                    2  => Ok(RequestType::HintColorRequestType),
+                    3  => Ok(RequestType::HintNumberRequestType),
+                    4  => Ok(RequestType::PlayCardRequestType),
+                    5  => Ok(RequestType::GameStartRequestType),
+                    6  => Ok(RequestType::RegisterRequestType),
+                    7  => Ok(RequestType::LoginRequestType),
+                    8  => Ok(RequestType::StatsRequestType),
+                    9  => Ok(RequestType::HelloRequestType),
+                    10 => Ok(RequestType::SyncRequestType),
+                    11 => Ok(RequestType::AdminRequestType),
+                    12 => Ok(RequestType::LegalActionsRequestType),
+                    13 => Ok(RequestType::SeatOrderRequestType),
+                    14 => Ok(RequestType::MoveCardRequestType),
+                    15 => Ok(RequestType::NoteRequestType),
+                    _  => unreachable!(),
+                }
+            })
+        })
+    }
+}
+
+impl Encodable for AdminCommand {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, index) = match *self {
+            AdminCommand::ListRooms => ("LIST_ROOMS", 0),
+            AdminCommand::Inspect   => ("INSPECT",    1),
+            AdminCommand::Kick      => ("KICK",       2),
+            AdminCommand::EndGame   => ("END_GAME",   3),
+            AdminCommand::Notice    => ("NOTICE",     4),
+            AdminCommand::Shutdown  => ("SHUTDOWN",   5),
+        };
+        enc.emit_enum("AdminCommand", |enc| {
+            enc.emit_enum_variant(name, index, 0, |_| {
+                Ok(())
+            })
+        })
+    }
+}
+
+impl Decodable for AdminCommand {
+    fn decode<D: Decoder>(d: &mut D) -> Result<AdminCommand, D::Error> {
+        d.read_enum("AdminCommand", |d|  {
+            let names = &["LIST_ROOMS", "INSPECT", "KICK", "END_GAME", "NOTICE", "SHUTDOWN"];
+            d.read_enum_variant(names, |_, i| {
+                match i {
+                    0 => Ok(AdminCommand::ListRooms),
+                    1 => Ok(AdminCommand::Inspect),
+                    2 => Ok(AdminCommand::Kick),
+                    3 => Ok(AdminCommand::EndGame),
+                    4 => Ok(AdminCommand::Notice),
+                    5 => Ok(AdminCommand::Shutdown),
                     _ => unreachable!(),
                 }
             })
         })
     }
 }
+
+impl Encodable for FinishReason {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, index) = match *self {
+            FinishReason::Strikes        => ("STRIKES",          0),
+            FinishReason::FinalRoundOver => ("FINAL_ROUND_OVER", 1),
+            FinishReason::Aborted        => ("ABORTED",          2),
+        };
+        enc.emit_enum("FinishReason", |enc| {
+            enc.emit_enum_variant(name, index, 0, |_| {
+                Ok(())
+            })
+        })
+    }
+}
+
+protocol_struct! {
+    /// Everything of the game state but the seed of the deck, which would let clients work out every card.
+    #[derive(RustcEncodable)]
+    pub struct PublicGameState<'g> {
+        phase:           GamePhase,
+        hint_tokens:     usize,
+        hint_tokens_max: usize,
+        err_tokens:      usize,
+        played_cards:    &'g HashMap<Color, Number>,
+        players:         &'g [Player],
+        host:            Option<Seat>,
+        first_player:    Seat,
+        deck:            &'g [Card],
+        discarded_cards: &'g [Card],
+        next_player:     Option<Seat>,
+        turns_left:      Option<usize>,
+        final_turns:     &'g [Seat],
+        turn:            usize,
+    }
+}
+
+impl<'g> PublicGameState<'g> {
+    pub fn new(game_state: &'g GameState) -> Self {
+        PublicGameState {
+            phase:           game_state.phase(),
+            hint_tokens:     game_state.hint_tokens(),
+            hint_tokens_max: game_state.hint_tokens_max(),
+            err_tokens:      game_state.err_tokens(),
+            played_cards:    game_state.played_cards(),
+            players:         game_state.players(),
+            host:            game_state.host(),
+            first_player:    game_state.first_player(),
+            deck:            game_state.deck(),
+            discarded_cards: game_state.discarded_cards(),
+            next_player:     game_state.next_player(),
+            turns_left:      game_state.turns_left(),
+            final_turns:     game_state.final_turns(),
+            turn:            game_state.turn(),
+        }
+    }
+}
+
+impl Encodable for GameState {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        PublicGameState::new(self).encode(enc)
+    }
+}
+
+/// Phases are objects with the phase's `name`, and a `reason` once the game is finished.
+impl Encodable for GamePhase {
+    fn encode<E: Encoder>(&self, enc: &mut E) -> Result<Void, E::Error> {
+        let (name, reason) = match *self {
+            GamePhase::Lobby               => ("LOBBY",       None),
+            GamePhase::Dealing             => ("DEALING",     None),
+            GamePhase::InProgress          => ("IN_PROGRESS", None),
+            GamePhase::FinalRound          => ("FINAL_ROUND", None),
+            GamePhase::Finished { reason } => ("FINISHED",    Some(reason)),
+        };
+        enc.emit_struct("GamePhase", 2, |enc| {
+            try!(enc.emit_struct_field("name", 0, |enc| name.encode(enc)));
+            enc.emit_struct_field("reason", 1, |enc| reason.encode(enc))
+        })
+    }
+}
@@ -1,11 +1,27 @@
+use cards;
 use rustc_serialize::{json, Decodable, Encodable};
 use rustc_serialize::json::Json;
 use ws::{CloseCode, Result, Sender};
+use crypto::util::fixed_time_eq;
+use std::borrow::Cow;
 use std::error::Error;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::result::Result as StdResult;
-use game_state::{CardPlayingResult, GameState, Void, DiscardCardResult};
-use connection::Connection;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use game_state::{Action, GamePhase, GameState, Outcome, RuleError, Seat, Void};
+use connection::{Connection, Peer};
+use config::Config;
+use storage::{GameStore, LoggedAction, SavedGame};
+use accounts::AccountStore;
+use stats::{GameTally, PlayerResult, StatsStore};
+use notes::{CardNote, GameNotes, MAX_NOTE_LEN};
+use protocol::{Capability, ClientProtocol, PROTOCOL_VERSION, MIN_PROTOCOL_VERSION, with_names};
+use delta::{Snapshot, StateDelta, StateUpdate};
+use monitoring::{Metrics, RoomInfo, RoomStatus};
+use serialization::to_json;
+use logging;
+use logging::{GameEvent, LogContext};
 use requests::RequestType::*;
 use requests::{
     RequestType,
@@ -14,7 +30,19 @@ use requests::{
     HintColorRequest,
     HintNumberRequest,
     PlayCardRequest,
-    GameStartRequest
+    GameStartRequest,
+    RegisterRequest,
+    LoginRequest,
+    StatsRequest,
+    HelloRequest,
+    SyncRequest,
+    AdminRequest,
+    LegalActionsRequest,
+    SeatOrderRequest,
+    MoveCardRequest,
+    NoteRequest,
+    AdminCommand,
+    RequestContext
 };
 use responses::error_messages::*;
 use responses::{
@@ -25,53 +53,273 @@ use responses::{
     HintColorResponse,
     HintNumberResponse,
     GameOverResponse,
-    GameStartResponse
+    GameStartResponse,
+    LoginResponse,
+    StatsResponse,
+    HelloResponse,
+    SyncResponse,
+    AdminResponse,
+    NoticeResponse,
+    LegalActionsResponse,
+    LegalCard,
+    LegalHint,
+    SeatOrderResponse,
+    MoveCardResponse,
+    NoteResponse
 };
 
+/// Time given to the clients to receive the close frames before the server stops.
+const SHUTDOWN_GRACE_MS: u64 = 1000;
+const DRAIN_NOTICE:      &'static str = "The server is shutting down, the game ends at the deadline.";
+const DRAIN_REASON:      &'static str = "The server is shutting down.";
+
 pub struct Server {
-    game_state:   GameState,
-    player_map:   BTreeMap<u8, String>,
-    connections:  Vec<Sender>,
-    game_started: bool,
+    game_id:       String,
+    game_state:    GameState,
+    player_map:    BTreeMap<usize, String>,
+    protocols:     BTreeMap<usize, ClientProtocol>,
+    request:       RequestContext,
+    connections:   BTreeMap<usize, Peer>,
+    state_version: usize,
+    store:         Option<GameStore>,
+    accounts:      Option<AccountStore>,
+    require_login: bool,
+    stats:         Option<StatsStore>,
+    tally:         GameTally,
+    notes:         GameNotes,
+    /// Names an administrator kicked, they may not join the game again.
+    kicked:        BTreeSet<String>,
+    metrics:       Metrics,
+    admin_token:   Option<String>,
+    random_seats:  bool,
+    /// Set once the host arranged the seats, which are then no longer shuffled.
+    seats_fixed:   bool,
+    control:       Option<Sender>,
+    draining:      bool,
+    /// Set once the end of the game has been recorded and announced, which must only happen once.
+    game_over:     bool,
 }
 
 impl Server {
-    pub fn new(game_state: GameState) -> Self {
+    pub fn new(config: &Config) -> Self {
         debug!("Creating new server instance.");
-        Server {
-            game_state:   game_state,
-            player_map:   BTreeMap::new(),
-            connections:  Vec::with_capacity(6),
-            game_started: false,
+        let store = config.data_dir.as_ref().and_then(|dir| {
+            GameStore::open(dir).map_err(|e| error!("Could not open game storage: {}.", e)).ok()
+        });
+        let accounts = config.accounts_file.as_ref().and_then(|path| {
+            AccountStore::open(path).map_err(|e| error!("Could not open account storage: {}.", e)).ok()
+        });
+        if config.require_login && accounts.is_none() {
+            warn!("Login is required but no accounts are available, nobody will be able to join.");
+        }
+        let stats = store.as_ref().and(config.data_dir.as_ref()).map(|dir| StatsStore::new(dir));
+        let saved = store.as_ref().and_then(|store| {
+            store.load_unfinished().unwrap_or_else(|e| {
+                error!("Could not load unfinished games: {}.", e);
+                None
+            })
+        });
+
+        let mut server = Server {
+            game_id:       GameStore::new_game_id(),
+            game_state:    Default::default(),
+            player_map:    BTreeMap::new(),
+            protocols:     BTreeMap::new(),
+            request:       Default::default(),
+            connections:   BTreeMap::new(),
+            state_version: 0,
+            store:         store,
+            accounts:      accounts,
+            require_login: config.require_login,
+            stats:         stats,
+            tally:         Default::default(),
+            notes:         Default::default(),
+            kicked:        BTreeSet::new(),
+            metrics:       Default::default(),
+            admin_token:   config.admin_token.clone(),
+            random_seats:  config.random_seats,
+            seats_fixed:   false,
+            control:       None,
+            draining:      false,
+            game_over:     false,
+        };
+        match saved {
+            Some(saved) => server.restore(saved),
+            None        => server.persist_new_game(),
+        }
+        server
+    }
+
+    fn persist_new_game(&self) {
+        if let Some(ref store) = self.store {
+            if let Err(e) = store.create(&self.game_id, &self.game_state.record()) {
+                error!("Could not persist new game {}: {}.", self.game_id, e);
+            }
+        }
+    }
+
+    fn restore(&mut self, saved: SavedGame) {
+        info!("Restoring game {} from {} logged actions.", saved.id, saved.actions.len());
+        let record = saved.record;
+        let seed = record.seed.unwrap_or_else(cards::new_seed);
+        let mut game_state = GameState::with_deck(record.hint_tokens_max, record.err_tokens, seed, record.deck);
+        let mut tally = GameTally::default();
+        let mut notes = GameNotes::default();
+        // only actions and moves bump the state version while the game is played
+        let mut state_version = 0;
+
+        for action in &saved.actions {
+            match *action {
+                LoggedAction::Join { ref name } => {
+                    if let Err(err_msg) = game_state.add_player(name) {
+                        warn!("Replaying join of player {} failed: {}.", name, err_msg);
+                    }
+                }
+                LoggedAction::Seats { ref names, ref first_player } => {
+                    let order = names.iter().map(|name| game_state.seat_of(name)).collect::<Option<Vec<_>>>();
+                    let first = names.iter().position(|name| name == first_player);
+                    match (order, first) {
+                        (Some(order), Some(first)) => {
+                            match game_state.arrange(&order, first) {
+                                Ok(())       => self.seats_fixed = true,
+                                Err(err_msg) => warn!("Replaying the seat order {:?} failed: {}.", names, err_msg),
+                            }
+                        }
+                        _ => warn!("Replaying the seat order {:?} failed: not every player has joined.", names),
+                    }
+                }
+                LoggedAction::Start => {
+                    if let Err(err_msg) = game_state.start() {
+                        warn!("Replaying start of the game failed: {}.", err_msg);
+                    }
+                }
+                LoggedAction::Move { ref player, card_id, to_slot } => {
+                    let result = game_state.seat_of(player).ok_or(RuleError::PlayerNotFound)
+                                           .and_then(|seat| game_state.move_card(seat, card_id, to_slot));
+                    match result {
+                        Ok(_)  => state_version += 1,
+                        Err(e) => warn!("Replaying move of card #{} by {} failed: {}.", card_id, player, e.explanation()),
+                    }
+                }
+                LoggedAction::Note { ref player, card_id, ref text } => {
+                    match game_state.seat_of(player) {
+                        Some(seat) => notes.set(seat, card_id, text),
+                        None       => warn!("Replaying a note of player {} who never joined failed.", player),
+                    }
+                }
+                LoggedAction::Kick { ref name } => {
+                    self.kicked.insert(name.clone());
+                }
+                _ => {
+                    let action = match action.action(&game_state) {
+                        Some(action) => action,
+                        None         => { warn!("Replaying an action of a player who never joined failed."); continue }
+                    };
+                    match game_state.apply(&action) {
+                        Ok(Outcome::Played { success: false, .. }) => tally.misplay(game_state.name_of(action.player())),
+                        Ok(Outcome::Hinted)                        => tally.clue_given(game_state.name_of(action.player())),
+                        Ok(_)                                      => {}
+                        Err(e) => { warn!("Replaying action {} failed: {}.", action, e.explanation()); continue }
+                    }
+                    state_version += 1;
+                }
+            }
+        }
+
+        self.state_version = state_version;
+        self.game_id       = saved.id;
+        self.game_state    = game_state;
+        self.tally         = tally;
+        self.notes         = notes;
+    }
+
+    /// The sender which is able to shut down the websocket event loop, and with it the server.
+    pub fn set_control(&mut self, control: Sender) {
+        self.control = Some(control);
+    }
+
+    pub fn add_connection(&mut self, con: &Connection) {
+        debug!("Registering Connection {}.", con.id);
+        self.connections.insert(con.id, con.peer());
+    }
+
+    pub fn remove_connection(&mut self, con: &Connection) {
+        if let Some(name) = self.player_map.remove(&con.id) {
+            info!("Player {} left, their seat is free for reconnection.", name);
+        }
+        self.protocols.remove(&con.id);
+        self.connections.remove(&con.id);
+    }
+
+    pub fn rooms(&self) -> Vec<RoomInfo> {
+        let phase = self.game_state.phase();
+        let status = if phase.is_finished() {
+            RoomStatus::Finished
+        } else if phase.is_started() {
+            RoomStatus::Running
+        } else {
+            RoomStatus::Waiting
+        };
+        vec![RoomInfo {
+            room_id:       self.game_id.clone(),
+            status:        status,
+            players:       self.game_state.player_names().iter().map(|n| (*n).to_owned()).collect(),
+            connections:   self.connections.len(),
+            state_version: self.state_version,
+            score:         self.game_state.score(),
+        }]
+    }
+
+    pub fn metrics(&self) -> String {
+        self.metrics.render(self.connections.len())
+    }
+
+    fn record(&self, action: LoggedAction) {
+        logging::event(&GameEvent::Action(&action));
+        if let Some(ref store) = self.store {
+            if let Err(e) = store.append(&self.game_id, &action) {
+                error!("Could not persist action for game {}: {}.", self.game_id, e);
+            }
         }
     }
 
     pub fn answer_with_error_msg(&self, explanation: &'static str, details: Option<&str>, con: &Connection) -> Result<Void> {
         info!("Sending Error Response: '{}'.", explanation);
-        let resp_json = json::encode(&ErrorResponse::new(explanation, details)).expect(CATASTROPHIC_FUCKUP);
-        con.out.send(resp_json)
+        let response = ErrorResponse::new(explanation, details, self.request.req_type, self.request_id());
+        self.metrics.error(error_code(explanation));
+        con.send(&self.encode_response(&response))
+    }
+
+    fn request_id(&self) -> Option<&str> {
+        self.request.request_id.as_ref().map(|id| id.as_str())
     }
 
-    fn encode_response<T>(&self, resp: &T) -> String
+    /// Responses are encoded into a json tree first, which each connection's wire format then serializes.
+    fn encode_response<T>(&self, resp: &T) -> Json
         where T: Encodable
     {
         debug!("Encoding Response.");
-        json::encode(&resp).expect(CATASTROPHIC_FUCKUP)
+        to_json(resp)
+    }
+
+    /// Clients speaking a version before seats, as well as those that never sent a handshake, are sent
+    /// the players' names wherever a response refers to a seat.
+    fn adapt<'r>(&self, id: usize, resp: &'r Json) -> Cow<'r, Json> {
+        match self.protocols.get(&id) {
+            Some(protocol) if protocol.has_seats() => Cow::Borrowed(resp),
+            _                                      => Cow::Owned(with_names(resp, &self.game_state.player_names())),
+        }
     }
 
-    fn is_connected(&self, id: u8) -> bool {
+    fn is_connected(&self, id: usize) -> bool {
         debug!("Check if id {} is connected.", id);
         self.player_map.contains_key(&id)
     }
 
-    pub fn handle_req(&mut self, req: &str, con: &Connection) -> Result<Void> {
+    pub fn handle_req(&mut self, req: &Json, con: &Connection) -> Result<Void> {
 
-        fn get_req_type(req: &str) -> StdResult<RequestType, Void> {
-            let req_json = match Json::from_str(&req) {
-                Ok(js) => js,
-                Err(_) => return Err(())
-            };
-            let req_obj = match req_json.as_object() {
+        fn get_req_type(req: &Json) -> StdResult<RequestType, Void> {
+            let req_obj = match req.as_object() {
                 Some(obj) => obj,
                 None      => return Err(())
             };
@@ -89,44 +337,88 @@ impl Server {
             }
         }
 
-        if let Some(name) = self.player_map.get(&con.id) {
-            if name != self.game_state.get_next_player() && !self.game_state.get_next_player().is_empty() {
-                return self.answer_with_error_msg(NOT_YOUR_TURN, None, &con)
-            }
+        fn get_req_id(req: &Json) -> Option<String> {
+            req.find("request_id").and_then(|id| id.as_string()).map(|id| id.to_owned())
         }
 
-        let req_type = match get_req_type(&req) {
-            Ok(t)  => t,
-            Err(_) => return self.answer_with_error_msg(UNABLE_TO_GET_MSG_TYPE, None, &con)
+        self.request = RequestContext {
+            req_type:   get_req_type(&req).ok(),
+            request_id: get_req_id(&req),
+        };
+        logging::set_context(LogContext {
+            room_id:       Some(self.game_id.clone()),
+            connection_id: Some(con.id),
+            player:        self.player_map.get(&con.id).cloned(),
+            request_type:  self.request.req_type,
+        });
+        let result = self.handle_current_req(&req, &con);
+        logging::clear_context();
+        self.request = Default::default();
+        result
+    }
+
+    fn handle_current_req(&mut self, req: &Json, con: &Connection) -> Result<Void> {
+        let req_type = match self.request.req_type {
+            Some(t) => t,
+            None    => return self.answer_with_error_msg(UNABLE_TO_GET_MSG_TYPE, None, &con)
         };
 
         info!("Received Request of type {:?} from Connection {}.", req_type, con.id);
         let already_connected = self.is_connected(con.id);
         let is_connecting     = req_type == ConnectionRequestType;
+        let is_logging_in     = req_type == RegisterRequestType || req_type == LoginRequestType;
+        // requests that are answered to anyone, whether they are playing or not
+        let is_open           = req_type == StatsRequestType || req_type == HelloRequestType || req_type == SyncRequestType
+                                || req_type == AdminRequestType;
+        let needs_connection  = !is_connecting && !is_logging_in && !is_open;
+        let skips_turn_check  = is_open || req_type == LegalActionsRequestType
+                                || req_type == MoveCardRequestType || req_type == NoteRequestType;
 
-        if already_connected && is_connecting {
+        if let Some(protocol) = self.protocols.get(&con.id) {
+            if !protocol.supports(&req_type) {
+                let details = format!("Negotiated protocol version is {}.", protocol.version);
+                return self.answer_with_error_msg(UNSUPPORTED_REQUEST, Some(&details), &con)
+            }
+        }
+
+        if let Some(name) = self.player_map.get(&con.id) {
+            let next_player = self.game_state.next_player();
+            if !skips_turn_check && next_player.is_some() && self.game_state.seat_of(name) != next_player {
+                return self.answer_with_error_msg(NOT_YOUR_TURN, None, &con)
+            }
+        }
+
+        if already_connected && (is_connecting || is_logging_in) {
             self.answer_with_error_msg(ALREADY_CONNECTED, None, &con)
-        } else if self.game_started && is_connecting {
-            self.answer_with_error_msg(CONN_GAME_ALREADY_STARTED, None, &con)
-        } else if !already_connected && !is_connecting {
+        } else if !already_connected && needs_connection {
             self.answer_with_error_msg(NOT_YET_CONNECTED, None, &con)
         } else {
             match req_type {
-                ConnectionRequestType  => self.dispatch_req::<ConnectionRequest>(&req, &con, &mut Self::handle_connection_request),
-                DiscardCardRequestType => self.dispatch_req::<DiscardCardRequest>(&req, &con, &mut Self::handle_discard_request),
-                HintColorRequestType   => self.dispatch_req::<HintColorRequest>(&req, &con, &mut Self::handle_hint_color_request),
-                HintNumberRequestType  => self.dispatch_req::<HintNumberRequest>(&req, &con, &mut Self::handle_hint_number_request),
-                PlayCardRequestType    => self.dispatch_req::<PlayCardRequest>(&req, &con, &mut Self::handle_play_card_request),
-                GameStartRequestType   => self.dispatch_req::<GameStartRequest>(&req, &con, &mut Self::handle_game_start_request),
+                ConnectionRequestType   => self.dispatch_req::<ConnectionRequest>(&req, &con, &mut Self::handle_connection_request),
+                DiscardCardRequestType  => self.dispatch_req::<DiscardCardRequest>(&req, &con, &mut Self::handle_discard_request),
+                HintColorRequestType    => self.dispatch_req::<HintColorRequest>(&req, &con, &mut Self::handle_hint_color_request),
+                HintNumberRequestType   => self.dispatch_req::<HintNumberRequest>(&req, &con, &mut Self::handle_hint_number_request),
+                PlayCardRequestType     => self.dispatch_req::<PlayCardRequest>(&req, &con, &mut Self::handle_play_card_request),
+                GameStartRequestType    => self.dispatch_req::<GameStartRequest>(&req, &con, &mut Self::handle_game_start_request),
+                RegisterRequestType     => self.dispatch_req::<RegisterRequest>(&req, &con, &mut Self::handle_register_request),
+                LoginRequestType        => self.dispatch_req::<LoginRequest>(&req, &con, &mut Self::handle_login_request),
+                StatsRequestType        => self.dispatch_req::<StatsRequest>(&req, &con, &mut Self::handle_stats_request),
+                HelloRequestType        => self.dispatch_req::<HelloRequest>(&req, &con, &mut Self::handle_hello_request),
+                SyncRequestType         => self.dispatch_req::<SyncRequest>(&req, &con, &mut Self::handle_sync_request),
+                AdminRequestType        => self.dispatch_req::<AdminRequest>(&req, &con, &mut Self::handle_admin_request),
+                LegalActionsRequestType => self.dispatch_req::<LegalActionsRequest>(&req, &con, &mut Self::handle_legal_actions_request),
+                SeatOrderRequestType    => self.dispatch_req::<SeatOrderRequest>(&req, &con, &mut Self::handle_seat_order_request),
+                MoveCardRequestType     => self.dispatch_req::<MoveCardRequest>(&req, &con, &mut Self::handle_move_card_request),
+                NoteRequestType         => self.dispatch_req::<NoteRequest>(&req, &con, &mut Self::handle_note_request),
             }
         }
     }
 
-    fn dispatch_req<T>(&mut self, req_str: &str, con: &Connection, dispatch_recv: &mut FnMut(&mut Self, &T, &Connection) -> Result<Void>) -> Result<Void>
+    fn dispatch_req<T>(&mut self, req: &Json, con: &Connection, dispatch_recv: &mut FnMut(&mut Self, &T, &Connection) -> Result<Void>) -> Result<Void>
         where T: Decodable
     {
         debug!("Dispatching Request.");
-        match json::decode::<T>(&req_str) {
+        match T::decode(&mut json::Decoder::new(req.clone())) {
             Ok(req) => dispatch_recv(self, &req, &con),
             Err(e)  => self.answer_with_error_msg(UNABLE_TO_DESERIALIZE_PAYLOAD, Some(e.description()), &con)
         }
@@ -134,12 +426,27 @@ impl Server {
 
     fn handle_connection_request(&mut self, req: &ConnectionRequest, con: &Connection) -> Result<Void> {
         info!("Handle Connection Request for player {} from Connection {}.", req.name, con.id);
+        if let Err(err_msg) = self.authorize(req) {
+            error!("Player {} is not authorized to join: {}.", req.name, err_msg);
+            return self.answer_with_error_msg(err_msg, None, &con);
+        }
+        if self.kicked.contains(&req.name) {
+            error!("Player {} was kicked and may not rejoin.", req.name);
+            return self.answer_with_error_msg(PLAYER_KICKED, None, &con);
+        }
+        if self.game_state.has_player(&req.name) {
+            return self.handle_reconnection(req, con);
+        } else if self.draining {
+            return self.answer_with_error_msg(SERVER_DRAINING, None, &con);
+        } else if self.game_state.phase() != GamePhase::Lobby {
+            return self.answer_with_error_msg(CONN_GAME_ALREADY_STARTED, None, &con);
+        }
         match self.game_state.add_player(req.name.as_str()) {
             Ok(_) => {
                 info!("Connection success.");
+                self.record(LoggedAction::Join { name: req.name.clone() });
                 self.player_map.insert(con.id, String::from(req.name.clone()));
-                self.connections.push(con.out.clone());
-                let response = &self.encode_response(&ConnectionResponse::new(self.player_map.values().map(|n| n.as_str()).collect::<Vec<&str>>()));
+                let response = &self.encode_response(&ConnectionResponse::new(self.player_map.values().map(|n| n.as_str()).collect::<Vec<&str>>(), self.request_id()));
                 self.answer_with_resp_msg(response, &con)
             }
             Err(err_msg) => {
@@ -149,113 +456,661 @@ impl Server {
         }
     }
 
-    fn handle_discard_request(&mut self, discard_req: &DiscardCardRequest, con: &Connection) -> Result<Void> {
-        info!("Handle Discard Request for card #{} from Connection {}.", discard_req.discarded_card_id, con.id);
-        let player = self.player_map.get(&con.id).unwrap();
-        match self.game_state.discard_card(player, discard_req.discarded_card_id) {
-            DiscardCardResult::Ok{discarded_card: discarded, drawn_card: drawn} => {
-                info!("Card #{} successfully discarded.", discard_req.discarded_card_id);
-                let response = &self.encode_response(&DiscardCardResponse::new(player, &discarded, drawn.as_ref(), &self.game_state));
-                self.answer_with_resp_msg(response, &con)
+    /// Names of registered accounts are reserved for their owners, anonymous players may only
+    /// pick unregistered names and only when the server does not require a login.
+    fn authorize(&self, req: &ConnectionRequest) -> StdResult<Void, &'static str> {
+        let accounts = match self.accounts {
+            Some(ref accounts)         => accounts,
+            None if self.require_login => return Err(LOGIN_REQUIRED),
+            None                       => return Ok(()),
+        };
+        match req.token {
+            Some(ref token) => match accounts.session_account(token) {
+                Some(account) if account == req.name => Ok(()),
+                _                                    => Err(INVALID_SESSION),
+            },
+            None if self.require_login || accounts.is_registered(&req.name) => Err(LOGIN_REQUIRED),
+            None => Ok(()),
+        }
+    }
+
+    fn handle_register_request(&mut self, req: &RegisterRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Register Request for account {} from Connection {}.", req.name, con.id);
+        let result = match self.accounts {
+            Some(ref mut accounts) => accounts.register(&req.name, &req.password),
+            None                   => Err(ACCOUNTS_DISABLED),
+        };
+        self.answer_with_session(&req.name, result, &con)
+    }
+
+    fn handle_login_request(&mut self, req: &LoginRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Login Request for account {} from Connection {}.", req.name, con.id);
+        let result = match self.accounts {
+            Some(ref mut accounts) => accounts.login(&req.name, &req.password),
+            None                   => Err(ACCOUNTS_DISABLED),
+        };
+        self.answer_with_session(&req.name, result, &con)
+    }
+
+    /// Session tokens are secrets, so unlike other responses they are only sent to the requester.
+    fn answer_with_session(&self, name: &str, result: StdResult<String, &'static str>, con: &Connection) -> Result<Void> {
+        match result {
+            Ok(token) => {
+                info!("Account {} logged in on Connection {}.", name, con.id);
+                con.send(&self.encode_response(&LoginResponse::new(name, &token, self.request_id())))
             }
-            DiscardCardResult::Err(err_msg) => {
-                error!("Card #{} could not be discarded: {}.", discard_req.discarded_card_id, err_msg);
-                self.answer_with_error_msg(err_msg, None, &con)
+            Err(err_msg) => self.answer_with_error_msg(err_msg, None, &con),
+        }
+    }
+
+    /// Clients that never send a handshake may use every request and capability, but are sent names
+    /// instead of seats like before version 3, so older clients keep working unchanged.
+    fn handle_hello_request(&mut self, req: &HelloRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Hello Request for protocol version {} from Connection {}.", req.protocol_version, con.id);
+        match ClientProtocol::negotiate(req.protocol_version, &req.capabilities) {
+            Some(protocol) => {
+                info!("Negotiated protocol version {} with Connection {}.", protocol.version, con.id);
+                let response = self.encode_response(&HelloResponse::new(protocol.version, &protocol.capabilities, self.request_id()));
+                self.protocols.insert(con.id, protocol);
+                con.send(&response)
+            }
+            None => {
+                error!("Connection {} speaks unsupported protocol version {}.", con.id, req.protocol_version);
+                let details = format!("Supported protocol versions are {} to {}.", MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+                self.answer_with_error_msg(INCOMPATIBLE_PROTOCOL_VERSION, Some(&details), &con)
             }
         }
     }
 
-    fn handle_hint_color_request(&mut self, hint_color_req: &HintColorRequest, con: &Connection) -> Result<Void> {
-        info!("Handle Hint Color Request for color {} from Connection {} for player {}.", hint_color_req.color, con.id, hint_color_req.target_player);
-        let player = self.player_map.get(&con.id).unwrap();
-        match self.game_state.hint_color(&hint_color_req.target_player, &hint_color_req.color) {
-            Ok(_) => {
-                info!("Color hint for player {} successful", hint_color_req.target_player);
-                let response = &self.encode_response(&HintColorResponse::new(
-                    player, &hint_color_req.target_player, &hint_color_req.color, &self.game_state));
-                self.answer_with_resp_msg(response, &con)
+    fn handle_stats_request(&mut self, req: &StatsRequest, con: &Connection) -> Result<Void> {
+        let name = match (req.name.as_ref(), self.player_map.get(&con.id)) {
+            (Some(name), _)    => name,
+            (None, Some(name)) => name,
+            (None, None)       => return self.answer_with_error_msg(PLAYER_NOT_FOUND, None, &con),
+        };
+        info!("Handle Stats Request for player {} from Connection {}.", name, con.id);
+        let stats = match self.stats {
+            Some(ref stats) => stats.player_stats(name),
+            None            => return self.answer_with_error_msg(STATS_DISABLED, None, &con),
+        };
+        match stats {
+            Ok(stats) => con.send(&self.encode_response(&StatsResponse::new(name, &stats, self.request_id()))),
+            Err(e)    => {
+                error!("Could not read statistics: {}.", e);
+                self.answer_with_error_msg(STATS_UNAVAILABLE, Some(&e.to_string()), &con)
             }
-            Err(err_msg) => {
-                error!("Color hint could not be given to player {}: {}", hint_color_req.target_player, err_msg);
-                self.answer_with_error_msg(err_msg, None, &con)
+        }
+    }
+
+    fn handle_sync_request(&mut self, _: &SyncRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Sync Request from Connection {}.", con.id);
+        con.send(&self.adapt(con.id, &self.encode_response(&SyncResponse::new(&self.game_state, self.notes_of(con), self.state_version, self.request_id()))))
+    }
+
+    fn handle_legal_actions_request(&mut self, _: &LegalActionsRequest, con: &Connection) -> Result<Void> {
+        let player = self.seat(con);
+        info!("Handle Legal Actions Request for seat {} from Connection {}.", player, con.id);
+        let (mut plays, mut discards, mut hints) = (Vec::new(), Vec::new(), Vec::new());
+        for action in self.game_state.legal_actions(player) {
+            let touched_card_ids = self.game_state.touched_cards(&action);
+            match action {
+                Action::Play { card_id, .. } => {
+                    plays.push(LegalCard { slot: self.game_state.slot(player, card_id).unwrap(), card_id: card_id });
+                }
+                Action::Discard { card_id, .. } => {
+                    discards.push(LegalCard { slot: self.game_state.slot(player, card_id).unwrap(), card_id: card_id });
+                }
+                Action::HintColor { target, color, .. } => {
+                    hints.push(LegalHint { target: target, color: Some(color), number: None, touched_card_ids: touched_card_ids });
+                }
+                Action::HintNumber { target, number, .. } => {
+                    hints.push(LegalHint { target: target, color: None, number: Some(number), touched_card_ids: touched_card_ids });
+                }
             }
         }
+        con.send(&self.adapt(con.id, &self.encode_response(&LegalActionsResponse::new(player, plays, discards, hints, self.request_id()))))
     }
 
-    fn handle_hint_number_request(&mut self, hint_number_req: &HintNumberRequest, con: &Connection) -> Result<Void> {
-        info!("Handle Hint Number Request for color {} from Connection {}.", hint_number_req.number, con.id);
-        let player = self.player_map.get(&con.id).unwrap();
-        match self.game_state.hint_number(&hint_number_req.target_player, &hint_number_req.number) {
-            Ok(_) => {
-                info!("Number hint for player {} successful", hint_number_req.target_player);
-                let response = &self.encode_response(&HintNumberResponse::new(
-                    player, &hint_number_req.target_player, &hint_number_req.number, &self.game_state));
-                self.answer_with_resp_msg(response, &con)
+    fn handle_seat_order_request(&mut self, req: &SeatOrderRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Seat Order Request {:?} from Connection {}.", req.seats, con.id);
+        if self.game_state.host() != Some(self.seat(con)) {
+            error!("Connection {} is not the host's and cannot arrange the seats.", con.id);
+            return self.answer_with_error_msg(NOT_HOST, None, &con);
+        }
+        if let Err(err_msg) = self.game_state.arrange(&req.seats, req.first_player.unwrap_or(0)) {
+            return self.answer_with_error_msg(err_msg, None, &con);
+        }
+        self.seats_fixed = true;
+        self.record(self.seating());
+        let response = self.encode_response(&SeatOrderResponse::new(&self.game_state, self.request_id()));
+        self.broadcast(&response)
+    }
+
+    /// The current seat order as it is logged, players are logged by name.
+    fn seating(&self) -> LoggedAction {
+        LoggedAction::Seats {
+            names:        self.game_state.player_names().iter().map(|n| (*n).to_owned()).collect(),
+            first_player: self.game_state.name_of(self.game_state.first_player()).to_owned(),
+        }
+    }
+
+    fn handle_admin_request(&mut self, req: &AdminRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Admin Request {:?} from Connection {}.", req.command, con.id);
+        let authorized = match self.admin_token {
+            Some(ref token) => fixed_time_eq(token.as_bytes(), req.admin_token.as_bytes()),
+            None            => return self.answer_with_error_msg(ADMIN_DISABLED, None, &con),
+        };
+        if !authorized {
+            error!("Connection {} sent an invalid admin token.", con.id);
+            return self.answer_with_error_msg(INVALID_ADMIN_TOKEN, None, &con);
+        }
+
+        let ack = self.encode_response(&AdminResponse::new(req.command, None, None, self.request_id()));
+        match req.command {
+            AdminCommand::ListRooms => {
+                con.send(&self.encode_response(&AdminResponse::new(req.command, Some(self.rooms()), None, self.request_id())))
             }
-            Err(err_msg) => {
-                error!("Number hint could not be given to player {}: {}", hint_number_req.target_player, err_msg);
-                self.answer_with_error_msg(err_msg, None, &con)
+            AdminCommand::Inspect => {
+                con.send(&self.adapt(con.id, &self.encode_response(&AdminResponse::new(req.command, None, Some(&self.game_state), self.request_id()))))
+            }
+            AdminCommand::Kick => {
+                let name = match req.player {
+                    Some(ref name) => name,
+                    None           => return self.answer_with_error_msg(UNABLE_TO_DESERIALIZE_PAYLOAD, Some("Missing field player."), &con),
+                };
+                let id = match self.player_map.iter().find(|&(_, n)| n == name) {
+                    Some((id, _)) => *id,
+                    None          => return self.answer_with_error_msg(PLAYER_NOT_FOUND, None, &con),
+                };
+                info!("Kicking player {} from Connection {}.", name, id);
+                self.kicked.insert(name.clone());
+                self.record(LoggedAction::Kick { name: name.clone() });
+                let reason = req.message.as_ref().map_or("Kicked by an administrator.", |m| m.as_str());
+                if let Some(peer) = self.connections.get(&id) {
+                    if let Err(e) = peer.close(CloseCode::Policy, reason) {
+                        error!("Could not close Connection {} of kicked player {}: {}.", id, name, e);
+                    }
+                }
+                con.send(&ack)
+            }
+            AdminCommand::EndGame => {
+                if let Err(err_msg) = self.game_state.abort() {
+                    return self.answer_with_error_msg(err_msg, None, &con);
+                }
+                try!(con.send(&ack));
+                self.end_game(Some(req.message.as_ref().map_or("Ended by an administrator.", |m| m.as_str())))
+            }
+            AdminCommand::Notice => {
+                let message = match req.message {
+                    Some(ref message) => message,
+                    None              => return self.answer_with_error_msg(UNABLE_TO_DESERIALIZE_PAYLOAD, Some("Missing field message."), &con),
+                };
+                try!(con.send(&ack));
+                self.broadcast(&self.encode_response(&NoticeResponse::new(message, None, None)))
+            }
+            AdminCommand::Shutdown => {
+                try!(con.send(&ack));
+                self.shutdown(req.message.as_ref().map_or("The server is shutting down.", |m| m.as_str()))
             }
         }
     }
 
-    fn handle_play_card_request(&mut self, play_card_req: &PlayCardRequest, con: &Connection) -> Result<Void> {
-        info!("Handle Play Card Request for card #{} from Connection {}.", play_card_req.played_card_id, con.id);
-        let player = self.player_map.get(&con.id).unwrap();
-        match self.game_state.play_card(player, play_card_req.played_card_id) {
-            CardPlayingResult::Ok {
-                success,
-                played_card,
-                drawn_card,
-            } => {
-                match success {
-                    true  => info!("Attempt of player {} to play card #{} was successful.", player, play_card_req.played_card_id),
-                    false => info!("Attempt of player {} to play card #{} has failed.", player, play_card_req.played_card_id),
+    /// Refuses new games from now on. A running game may go on until the deadline, which its players
+    /// are told about. Its actions are already persisted, so it can be resumed after a restart.
+    pub fn drain(&mut self, grace: Duration) -> Result<Void> {
+        self.draining = true;
+        let phase = self.game_state.phase();
+        if !phase.is_started() || phase.is_finished() {
+            return self.shutdown(DRAIN_REASON);
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        info!("Draining, game {} may go on for {} seconds.", self.game_id, grace.as_secs());
+        if self.store.is_some() {
+            info!("Game {} is persisted and resumes after a restart if it is not over by then.", self.game_id);
+        }
+        self.broadcast(&self.encode_response(&NoticeResponse::new(DRAIN_NOTICE, Some(now + grace.as_secs()), None)))
+    }
+
+    /// Tells every client why the server goes away and closes all connections, then stops the
+    /// event loop once the clients had a moment to receive their close frames.
+    pub fn shutdown(&self, reason: &str) -> Result<Void> {
+        info!("Shutting down: {}", reason);
+        try!(self.broadcast(&self.encode_response(&NoticeResponse::new(reason, None, None))));
+        for peer in self.connections.values() {
+            try!(peer.close(CloseCode::Away, reason));
+        }
+        self.stop();
+        Ok(())
+    }
+
+    fn stop(&self) {
+        if let Some(control) = self.control.clone() {
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(SHUTDOWN_GRACE_MS));
+                if let Err(e) = control.shutdown() {
+                    error!("Could not stop the websocket server: {}.", e);
                 }
-                let response = &self.encode_response(
-                    &PlayCardResponse::new(&player, &played_card, drawn_card.as_ref(), success, &self.game_state));
-                self.answer_with_resp_msg(response, &con)
+            });
+        }
+    }
+
+    fn handle_reconnection(&mut self, req: &ConnectionRequest, con: &Connection) -> Result<Void> {
+        if self.player_map.values().any(|n| *n == req.name) {
+            error!("Player {} is already seated on another connection.", req.name);
+            return self.answer_with_error_msg(PLAYER_ALREADY_EXISTS, None, &con);
+        }
+        info!("Player {} reconnected on Connection {}.", req.name, con.id);
+        self.player_map.insert(con.id, req.name.clone());
+        let response = self.encode_response(&ConnectionResponse::new(self.player_map.values().map(|n| n.as_str()).collect::<Vec<&str>>(), self.request_id()));
+        try!(self.answer_with_resp_msg(&response, &con));
+        if self.game_state.phase().is_started() {
+            debug!("Sending current game state to reconnected player {}.", req.name);
+            try!(con.send(&self.adapt(con.id, &self.encode_response(&GameStartResponse::new(&self.game_state, self.notes_of(con), self.state_version, self.request_id())))));
+        }
+        Ok(())
+    }
+
+    fn handle_discard_request(&mut self, discard_req: &DiscardCardRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Discard Request for card #{:?} in slot {:?} from Connection {}.", discard_req.discarded_card_id, discard_req.slot, con.id);
+        let player = self.seat(con);
+        let card_id = match self.card_id(player, discard_req.discarded_card_id, discard_req.slot) {
+            Some(card_id) => card_id,
+            None          => return self.answer_with_error_msg(CARD_NOT_FOUND, None, &con),
+        };
+        self.handle_action(Action::Discard { player: player, card_id: card_id }, con)
+    }
+
+    fn handle_hint_color_request(&mut self, hint_color_req: &HintColorRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Hint Color Request for color {} from Connection {}.", hint_color_req.color, con.id);
+        let target = match self.target_seat(hint_color_req.target_seat, hint_color_req.target_player.as_ref()) {
+            Some(target) => target,
+            None         => return self.answer_with_error_msg(PLAYER_NOT_FOUND, None, &con),
+        };
+        let player = self.seat(con);
+        self.handle_action(Action::HintColor { player: player, target: target, color: hint_color_req.color }, con)
+    }
+
+    fn handle_hint_number_request(&mut self, hint_number_req: &HintNumberRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Hint Number Request for number {} from Connection {}.", hint_number_req.number, con.id);
+        let target = match self.target_seat(hint_number_req.target_seat, hint_number_req.target_player.as_ref()) {
+            Some(target) => target,
+            None         => return self.answer_with_error_msg(PLAYER_NOT_FOUND, None, &con),
+        };
+        let player = self.seat(con);
+        self.handle_action(Action::HintNumber { player: player, target: target, number: hint_number_req.number }, con)
+    }
+
+    fn handle_play_card_request(&mut self, play_card_req: &PlayCardRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Play Card Request for card #{:?} in slot {:?} from Connection {}.", play_card_req.played_card_id, play_card_req.slot, con.id);
+        let player = self.seat(con);
+        let card_id = match self.card_id(player, play_card_req.played_card_id, play_card_req.slot) {
+            Some(card_id) => card_id,
+            None          => return self.answer_with_error_msg(CARD_NOT_FOUND, None, &con),
+        };
+        self.handle_action(Action::Play { player: player, card_id: card_id }, con)
+    }
+
+    /// The seat of the player on a connection, every connected player has joined the game.
+    fn seat(&self, con: &Connection) -> Seat {
+        self.game_state.seat_of(&self.player_map[&con.id]).unwrap()
+    }
+
+    /// Rearranging a hand is broadcast like a turn, but may be done at any time.
+    fn handle_move_card_request(&mut self, req: &MoveCardRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Move Card Request for card #{:?} in slot {:?} to slot {} from Connection {}.", req.card_id, req.slot, req.to_slot, con.id);
+        let player = self.seat(con);
+        let card_id = match self.card_id(player, req.card_id, req.slot) {
+            Some(card_id) => card_id,
+            None          => return self.answer_with_error_msg(CARD_NOT_FOUND, None, &con),
+        };
+        let from_slot = match self.game_state.move_card(player, card_id, req.to_slot) {
+            Ok(from_slot) => from_slot,
+            Err(e)        => return self.answer_with_error_msg(e.explanation(), None, &con),
+        };
+        self.record(LoggedAction::Move { player: self.game_state.name_of(player).to_owned(), card_id: card_id, to_slot: req.to_slot });
+        self.state_version += 1;
+        let response = self.encode_response(&MoveCardResponse::new(player, card_id, from_slot, req.to_slot, self.state_version, self.request_id()));
+        self.broadcast(&response)
+    }
+
+    fn handle_note_request(&mut self, req: &NoteRequest, con: &Connection) -> Result<Void> {
+        info!("Handle Note Request for card #{} from Connection {}.", req.card_id, con.id);
+        if req.text.chars().count() > MAX_NOTE_LEN {
+            return self.answer_with_error_msg(NOTE_TOO_LONG, None, &con);
+        }
+        if let Err(e) = self.game_state.validate_note(req.card_id) {
+            return self.answer_with_error_msg(e.explanation(), None, &con);
+        }
+        let player = self.seat(con);
+        self.notes.set(player, req.card_id, &req.text);
+        self.record(LoggedAction::Note { player: self.game_state.name_of(player).to_owned(), card_id: req.card_id, text: req.text.clone() });
+        con.send(&self.encode_response(&NoteResponse::new(req.card_id, &req.text, self.request_id())))
+    }
+
+    /// The notes of the player on a connection, none if it has not joined the game.
+    fn notes_of(&self, con: &Connection) -> Vec<CardNote> {
+        self.player_map.get(&con.id).and_then(|name| self.game_state.seat_of(name)).map_or(Vec::new(), |seat| self.notes.by(seat))
+    }
+
+    /// A card given by id is taken as is, one given by slot has to be in the player's hand.
+    fn card_id(&self, seat: Seat, card_id: Option<usize>, slot: Option<usize>) -> Option<usize> {
+        card_id.or_else(|| slot.and_then(|slot| self.game_state.card_at(seat, slot)))
+    }
+
+    /// A hint target given by seat is taken as is, one given by name has to be seated.
+    fn target_seat(&self, seat: Option<Seat>, name: Option<&String>) -> Option<Seat> {
+        seat.or_else(|| name.and_then(|name| self.game_state.seat_of(name)))
+    }
+
+    /// Applies a game action and sends every client the response for it, along with the new state.
+    fn handle_action(&mut self, action: Action, con: &Connection) -> Result<Void> {
+        let before = Snapshot::of(&self.game_state);
+        let outcome = match self.game_state.apply(&action) {
+            Ok(outcome) => outcome,
+            Err(e)      => return self.answer_with_error_msg(e.explanation(), None, &con),
+        };
+        info!("Action {} was applied.", action);
+        self.record(LoggedAction::of(&action, &self.game_state));
+        match outcome {
+            Outcome::Played { success: false, .. } => self.tally.misplay(self.game_state.name_of(action.player())),
+            Outcome::Hinted                        => self.tally.clue_given(self.game_state.name_of(action.player())),
+            _                                      => {}
+        }
+        self.state_version += 1;
+        let delta = StateDelta::between(&before, &self.game_state);
+        let full = self.encode_action_response(&action, &outcome, StateUpdate::Full(&self.game_state));
+        let partial = self.encode_action_response(&action, &outcome, StateUpdate::Delta(&delta));
+        self.answer_with_state_msgs(&full, &partial, &con)
+    }
+
+    fn encode_action_response(&self, action: &Action, outcome: &Outcome, state: StateUpdate) -> Json {
+        let request_id = self.request_id();
+        match (action, outcome) {
+            (&Action::Discard { player, .. }, &Outcome::Discarded { slot, ref discarded_card, ref drawn_card }) => {
+                self.encode_response(&DiscardCardResponse::new(player, slot, discarded_card, drawn_card.as_ref(), state, self.state_version, request_id))
             }
-            CardPlayingResult::EpicFail => {
-                info!("Attempt to play Card #{} has failed and all error tokens are used up.", play_card_req.played_card_id);
-                self.game_over(&con)
+            (&Action::Play { player, .. }, &Outcome::Played { slot, success, ref played_card, ref drawn_card }) => {
+                self.encode_response(&PlayCardResponse::new(player, slot, played_card, drawn_card.as_ref(), success, state, self.state_version, request_id))
             }
-            CardPlayingResult::Err(err_msg) => {
-                error!("Error when player {} tried to play card #{}", player, play_card_req.played_card_id);
-                self.answer_with_error_msg(err_msg, None, &con)
+            (&Action::HintColor { player, target, ref color }, _) => {
+                self.encode_response(&HintColorResponse::new(player, target, color, state, self.state_version, request_id))
             }
+            (&Action::HintNumber { player, target, ref number }, _) => {
+                self.encode_response(&HintNumberResponse::new(player, target, number, state, self.state_version, request_id))
+            }
+            _ => unreachable!(),
         }
     }
 
     fn handle_game_start_request(&mut self, _: &GameStartRequest, con: &Connection) -> Result<Void> {
-        if self.game_started {
-            error!("Received request to start game after it was started already.");
-            self.answer_with_error_msg(GAME_ALREADY_STARTED, None, &con)
+        if self.draining {
+            error!("Received request to start game while draining.");
+            return self.answer_with_error_msg(SERVER_DRAINING, None, &con);
+        }
+        if let Err(err_msg) = self.game_state.check_start() {
+            error!("Could not start the game: {}.", err_msg);
+            return self.answer_with_error_msg(err_msg, None, &con);
+        }
+        // only shuffled once the game is sure to start, so a rejected start leaves the seats alone
+        if self.random_seats && !self.seats_fixed {
+            if let Err(err_msg) = self.game_state.shuffle_seats() {
+                return self.answer_with_error_msg(err_msg, None, &con);
+            }
+            self.record(self.seating());
+        }
+        if let Err(err_msg) = self.game_state.start() {
+            error!("Could not start the game: {}.", err_msg);
+            self.answer_with_error_msg(err_msg, None, &con)
         } else {
             info!("Starting game.");
-            self.game_started = true;
-            let response = &self.encode_response(&GameStartResponse::new(&self.game_state));
+            self.metrics.game_started();
+            self.record(LoggedAction::Start);
+            let response = &self.encode_response(&GameStartResponse::new(&self.game_state, Vec::new(), self.state_version, self.request_id()));
             self.answer_with_resp_msg(response, &con)
         }
     }
 
-    fn answer_with_resp_msg(&self, resp: &str, con: &Connection) -> Result<Void> {
+    fn answer_with_resp_msg(&mut self, resp: &Json, con: &Connection) -> Result<Void> {
         debug!("Dispatching reponse for connection {}.", con.id);
-        if let Some(0) = self.game_state.turns_left() {
-            return self.game_over(&con);
+        if self.game_state.phase().is_finished() && !self.game_over {
+            return self.end_game(None);
+        }
+        self.broadcast(resp)
+    }
+
+    fn broadcast(&self, resp: &Json) -> Result<Void> {
+        for (id, peer) in &self.connections {
+            try!(peer.send(&self.adapt(*id, resp)));
         }
-        con.out.broadcast(resp)
+        Ok(())
+    }
+
+    /// Clients that negotiated the `DELTAS` capability only receive the changes to the game state.
+    fn answer_with_state_msgs(&mut self, full: &Json, delta: &Json, con: &Connection) -> Result<Void> {
+        debug!("Dispatching state update for connection {}.", con.id);
+        if self.game_state.phase().is_finished() && !self.game_over {
+            return self.end_game(None);
+        }
+        for (id, peer) in &self.connections {
+            let wants_delta = self.protocols.get(id).map_or(false, |p| p.has(Capability::Deltas));
+            try!(peer.send(&self.adapt(*id, if wants_delta { delta } else { full })));
+        }
+        Ok(())
     }
 
-    fn game_over(&self, con: &Connection) -> Result<Void> {
+    /// The reason is only given when the game ends before it is played out.
+    fn end_game(&mut self, reason: Option<&str>) -> Result<Void> {
+        self.game_over = true;
         let score = self.game_state.score();
         info!("Game Over! Final score: {}.", score);
-        let response = self.encode_response(&GameOverResponse::new(score));
-        con.out.broadcast(response.as_str()).unwrap();
-        for out in &self.connections {
-            out.close(CloseCode::Normal).unwrap();
+        self.metrics.game_finished(score);
+        logging::event(&GameEvent::GameOver { score: score });
+        if let Some(ref store) = self.store {
+            match store.finish(&self.game_id) {
+                Ok(_)  => self.record_results(score),
+                Err(e) => error!("Could not mark game {} as finished: {}.", self.game_id, e),
+            }
+        }
+        let response = self.encode_response(&GameOverResponse::new(score, reason, self.notes.all(), self.request_id()));
+        // a client failing to take the news must not keep the others from getting it
+        for (id, peer) in &self.connections {
+            if let Err(e) = peer.send(&self.adapt(*id, &response)) {
+                error!("Could not send the end of the game to Connection {}: {}.", id, e);
+            }
+            if let Err(e) = peer.close(CloseCode::Normal, reason.unwrap_or("Game over.")) {
+                error!("Could not close Connection {}: {}.", id, e);
+            }
+        }
+        if self.draining {
+            self.stop();
         }
         Ok(())
     }
 
+    fn record_results(&self, score: usize) {
+        let stats = match self.stats {
+            Some(ref stats) => stats,
+            None            => return,
+        };
+        let names = self.game_state.player_names();
+        let results = names.iter().map(|name| {
+            let (clues_given, misplays) = self.tally.get(name).map_or((0, 0), |t| (t.clues_given, t.misplays));
+            PlayerResult {
+                name:        (*name).to_owned(),
+                game_id:     self.game_id.clone(),
+                score:       score,
+                players:     names.len(),
+                variant:     self.game_state.variant().to_owned(),
+                strikes:     self.tally.strikes(),
+                clues_given: clues_given,
+                misplays:    misplays,
+            }
+        }).collect::<Vec<_>>();
+        if let Err(e) = stats.append(&results) {
+            error!("Could not persist results of game {}: {}.", self.game_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cards::{new_deck, Seed};
+    use storage::GameRecord;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::sync::mpsc::{sync_channel, Receiver};
+
+    fn server() -> Server {
+        Server::new(&Config {
+            data_dir:      None,
+            accounts_file: None,
+            require_login: false,
+            tcp_addr:      None,
+            tls_cert:      None,
+            tls_key:       None,
+            http_addr:     None,
+            json_logs:     false,
+            admin_token:   None,
+            drain_secs:    0,
+            random_seats:  false,
+        })
+    }
+
+    /// Restores a game with two players that has been logged up to `actions`, the game started
+    /// from a deck shuffled by `seed`.
+    fn restored(seed: Seed, actions: Vec<LoggedAction>) -> Server {
+        let record = GameRecord { hint_tokens_max: 8, err_tokens: 3, seed: Some(seed), deck: new_deck(&seed) };
+        let mut logged = vec![
+            LoggedAction::Join { name: "alice".to_owned() },
+            LoggedAction::Join { name: "bob".to_owned() },
+            LoggedAction::Start,
+        ];
+        logged.extend(actions);
+        let mut server = server();
+        server.restore(SavedGame { id: "restored".to_owned(), record: record, actions: logged });
+        server
+    }
+
+    fn hand(game_state: &GameState, player: Seat) -> Vec<usize> {
+        game_state.players()[player].cards.iter().map(|c| c.card.id).collect()
+    }
+
+    /// A TCP client whose messages are kept for the test instead of being written to its socket.
+    struct Client {
+        con:       Connection,
+        lines:     Receiver<String>,
+        _listener: TcpListener,
+    }
+
+    impl Client {
+        fn join(server: &mut Server, name: &str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            let (sender, lines) = sync_channel(64);
+            let con = Connection::new(Connection::next_id(), Peer::Tcp { lines: sender, stream: Arc::new(stream) });
+            server.add_connection(&con);
+            let client = Client { con: con, lines: lines, _listener: listener };
+            client.request(server, &format!(r#"{{"msg_type": "CONNECTION_REQUEST", "name": "{}"}}"#, name));
+            client
+        }
+
+        fn request(&self, server: &mut Server, req: &str) {
+            server.handle_req(&Json::from_str(req).unwrap(), &self.con).unwrap();
+        }
+
+        /// The messages received since the last call.
+        fn received(&self) -> Vec<Json> {
+            self.lines.try_iter().map(|line| Json::from_str(&line).unwrap()).collect()
+        }
+    }
+
+    /// The texts of the notes in a response.
+    fn note_texts(resp: &Json) -> Vec<&str> {
+        resp.find("notes").and_then(|n| n.as_array()).unwrap().iter().map(|n| n.find("text").unwrap().as_string().unwrap()).collect()
+    }
+
+    #[test]
+    fn replayed_moves_keep_the_hand_order() {
+        let seed = [3; 8];
+        let mut live = GameState::with_deck(8, 3, seed, new_deck(&seed));
+        live.add_player("alice").unwrap();
+        live.add_player("bob").unwrap();
+        live.start().unwrap();
+        let moved = live.card_at(0, 3).unwrap();
+        live.move_card(0, moved, 0).unwrap();
+        let discarded = live.card_at(0, 1).unwrap();
+        live.apply(&Action::Discard { player: 0, card_id: discarded }).unwrap();
+        let moved_back = live.card_at(0, 0).unwrap();
+        live.move_card(0, moved_back, 4).unwrap();
+
+        let server = restored(seed, vec![
+            LoggedAction::Move { player: "alice".to_owned(), card_id: moved, to_slot: 0 },
+            LoggedAction::Discard { player: "alice".to_owned(), card_id: discarded },
+            LoggedAction::Move { player: "alice".to_owned(), card_id: moved_back, to_slot: 4 },
+        ]);
+        assert_eq!(hand(&server.game_state, 0), hand(&live, 0));
+        assert_eq!(hand(&server.game_state, 1), hand(&live, 1));
+        assert_eq!(server.state_version, 3);
+    }
+
+    #[test]
+    fn notes_are_private_until_the_game_is_over() {
+        let mut server = server();
+        let alice = Client::join(&mut server, "alice");
+        let bob = Client::join(&mut server, "bob");
+        alice.request(&mut server, r#"{"msg_type": "GAME_START_REQUEST"}"#);
+        let card_id = server.game_state.card_at(1, 0).unwrap();
+        alice.received();
+        bob.received();
+
+        alice.request(&mut server, &format!(r#"{{"msg_type": "NOTE_REQUEST", "card_id": {}, "text": "maybe r1"}}"#, card_id));
+        assert_eq!(alice.received()[0].find("msg_type").unwrap().as_string(), Some("NOTE_RESPONSE"));
+        assert!(bob.received().is_empty());
+
+        alice.request(&mut server, r#"{"msg_type": "SYNC_REQUEST"}"#);
+        bob.request(&mut server, r#"{"msg_type": "SYNC_REQUEST"}"#);
+        assert_eq!(note_texts(&alice.received()[0]), vec!["maybe r1"]);
+        assert!(note_texts(&bob.received()[0]).is_empty());
+
+        server.game_state.abort().unwrap();
+        server.end_game(None).unwrap();
+        for client in &[alice, bob] {
+            let game_over = client.received().pop().unwrap();
+            assert_eq!(game_over.find("msg_type").unwrap().as_string(), Some("GAME_OVER_RESPONSE"));
+            assert_eq!(note_texts(&game_over), vec!["maybe r1"]);
+        }
+    }
+
+    #[test]
+    fn notes_are_limited_in_length() {
+        let mut server = server();
+        let alice = Client::join(&mut server, "alice");
+        Client::join(&mut server, "bob");
+        alice.request(&mut server, r#"{"msg_type": "GAME_START_REQUEST"}"#);
+        let card_id = server.game_state.card_at(0, 0).unwrap();
+        alice.received();
+
+        // the limit counts characters rather than bytes
+        let note = |len| format!(r#"{{"msg_type": "NOTE_REQUEST", "card_id": {}, "text": "{}"}}"#, card_id, "\u{e9}".repeat(len));
+        alice.request(&mut server, &note(MAX_NOTE_LEN));
+        assert_eq!(alice.received()[0].find("msg_type").unwrap().as_string(), Some("NOTE_RESPONSE"));
+        alice.request(&mut server, &note(MAX_NOTE_LEN + 1));
+        assert_eq!(alice.received()[0].find("error_code").unwrap().as_string(), Some("NOTE_TOO_LONG"));
+        assert_eq!(server.notes.by(0)[0].text.chars().count(), MAX_NOTE_LEN);
+    }
+
+    #[test]
+    fn notes_are_restored() {
+        let seed = [5; 8];
+        let card_id = restored(seed, Vec::new()).game_state.card_at(1, 2).unwrap();
+        let server = restored(seed, vec![
+            LoggedAction::Note { player: "bob".to_owned(), card_id: card_id, text: "keep".to_owned() },
+            LoggedAction::Note { player: "alice".to_owned(), card_id: card_id, text: "gone".to_owned() },
+            LoggedAction::Note { player: "alice".to_owned(), card_id: card_id, text: "".to_owned() },
+        ]);
+        let notes = server.notes.all();
+        assert_eq!(notes.len(), 1);
+        assert_eq!((notes[0].author, notes[0].card_id, notes[0].text.as_str()), (1, card_id, "keep"));
+    }
 }
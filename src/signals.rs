@@ -0,0 +1,44 @@
+use libc;
+use libc::c_int;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use server::Server;
+
+const POLL_MS:         u64          = 100;
+const DEADLINE_REASON: &'static str = "The server is shutting down, the drain deadline has passed.";
+
+static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(_: c_int) {
+    SIGNALS.fetch_add(1, Ordering::SeqCst);
+}
+
+/// The first SIGTERM or SIGINT puts the server into drain mode, it shuts down once the grace
+/// period is over or another signal arrives. The handler only counts signals, the server is
+/// locked from a watcher thread which polls the count.
+pub fn watch(server: Arc<Mutex<Server>>, grace: Duration) {
+    let handler = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+    thread::spawn(move || {
+        while SIGNALS.load(Ordering::SeqCst) == 0 {
+            thread::sleep(Duration::from_millis(POLL_MS));
+        }
+        info!("Received signal, draining the server.");
+        if let Err(e) = server.lock().unwrap().drain(grace) {
+            error!("Could not notify clients about draining: {}.", e);
+        }
+
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline && SIGNALS.load(Ordering::SeqCst) < 2 {
+            thread::sleep(Duration::from_millis(POLL_MS));
+        }
+        if let Err(e) = server.lock().unwrap().shutdown(DEADLINE_REASON) {
+            error!("Could not close all connections: {}.", e);
+        }
+    });
+}
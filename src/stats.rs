@@ -0,0 +1,140 @@
+use rustc_serialize::json;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use game_state::Void;
+use responses::error_messages::CATASTROPHIC_FUCKUP;
+
+const STATS_FILE:     &'static str = "results.jsonl";
+const RECENT_RESULTS: usize        = 10;
+
+/// Per-player counters collected while a game is running.
+#[derive(Default)]
+pub struct PlayerTally {
+    pub clues_given: usize,
+    pub misplays:    usize,
+}
+
+#[derive(Default)]
+pub struct GameTally {
+    players: BTreeMap<String, PlayerTally>,
+}
+
+impl GameTally {
+    pub fn clue_given(&mut self, player: &str) {
+        self.players.entry(player.to_owned()).or_insert_with(Default::default).clues_given += 1;
+    }
+
+    pub fn misplay(&mut self, player: &str) {
+        self.players.entry(player.to_owned()).or_insert_with(Default::default).misplays += 1;
+    }
+
+    pub fn strikes(&self) -> usize {
+        self.players.values().map(|t| t.misplays).fold(0, |x, y| x + y)
+    }
+
+    pub fn get(&self, player: &str) -> Option<&PlayerTally> {
+        self.players.get(player)
+    }
+}
+
+protocol_struct! {
+    /// The outcome of a single finished game from the point of view of one player.
+    #[derive(RustcEncodable, RustcDecodable, Clone)]
+    pub struct PlayerResult {
+        pub name:        String,
+        pub game_id:     String,
+        pub score:       usize,
+        pub players:     usize,
+        pub variant:     String,
+        pub strikes:     usize,
+        pub clues_given: usize,
+        pub misplays:    usize,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct VariantStats {
+        pub variant:       String,
+        pub games_played:  usize,
+        pub average_score: f64,
+        pub best_score:    usize,
+    }
+}
+
+protocol_struct! {
+    #[derive(RustcEncodable)]
+    pub struct PlayerStats {
+        pub games_played: usize,
+        pub best_score:   usize,
+        pub variants:     Vec<VariantStats>,
+        pub recent_games: Vec<PlayerResult>,
+    }
+}
+
+impl PlayerStats {
+    fn from_results(results: &[PlayerResult]) -> Self {
+        let mut by_variant: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for result in results {
+            by_variant.entry(&result.variant).or_insert_with(Vec::new).push(result.score);
+        }
+        let variants = by_variant.into_iter().map(|(variant, scores)| {
+            VariantStats {
+                variant:       variant.to_owned(),
+                games_played:  scores.len(),
+                average_score: scores.iter().fold(0, |x, y| x + y) as f64 / scores.len() as f64,
+                best_score:    scores.iter().cloned().max().unwrap_or(0),
+            }
+        }).collect();
+
+        PlayerStats {
+            games_played: results.len(),
+            best_score:   results.iter().map(|r| r.score).max().unwrap_or(0),
+            variants:     variants,
+            recent_games: results.iter().rev().take(RECENT_RESULTS).cloned().collect(),
+        }
+    }
+}
+
+/// Results of all finished games, one `PlayerResult` per line.
+pub struct StatsStore {
+    path: PathBuf,
+}
+
+impl StatsStore {
+    pub fn new(dir: &Path) -> Self {
+        StatsStore {
+            path: dir.join(STATS_FILE),
+        }
+    }
+
+    pub fn append(&self, results: &[PlayerResult]) -> io::Result<Void> {
+        debug!("Persisting {} player results.", results.len());
+        let mut file = try!(OpenOptions::new().create(true).append(true).open(&self.path));
+        for result in results {
+            try!(writeln!(file, "{}", json::encode(result).expect(CATASTROPHIC_FUCKUP)));
+        }
+        file.sync_data()
+    }
+
+    pub fn player_stats(&self, name: &str) -> io::Result<PlayerStats> {
+        debug!("Collecting statistics of player {}.", name);
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(PlayerStats::from_results(&[])),
+            Err(e) => return Err(e),
+        };
+        let mut results = Vec::new();
+        for line in BufReader::new(file).lines() {
+            match json::decode::<PlayerResult>(&try!(line)) {
+                Ok(ref result) if result.name != name => {}
+                Ok(result) => results.push(result),
+                Err(e)     => warn!("Skipping unreadable player result: {}.", e),
+            }
+        }
+        Ok(PlayerStats::from_results(&results))
+    }
+}
@@ -0,0 +1,182 @@
+use rustc_serialize::json;
+use rand;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use cards::{Card, Color, Number, Seed};
+use game_state::{Action, GameState, Void};
+use responses::error_messages::CATASTROPHIC_FUCKUP;
+
+const RUNNING_EXT:  &'static str = "game";
+const FINISHED_EXT: &'static str = "finished";
+
+/// Everything that is needed to deal out a game exactly as it was dealt before.
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct GameRecord {
+    pub hint_tokens_max: usize,
+    pub err_tokens:      usize,
+    /// Missing in the records of games saved before games were seeded.
+    pub seed:            Option<Seed>,
+    pub deck:            Vec<Card>,
+}
+
+/// A single accepted action, logged in the order the server has applied it. Players are logged by
+/// name, so the log reads the same whatever seats they were given.
+#[derive(RustcEncodable, RustcDecodable)]
+pub enum LoggedAction {
+    Join       { name: String },
+    Seats      { names: Vec<String>, first_player: String },
+    Start,
+    Discard    { player: String, card_id: usize },
+    Play       { player: String, card_id: usize },
+    HintColor  { player: String, target: String, color: Color },
+    HintNumber { player: String, target: String, number: Number },
+    Move       { player: String, card_id: usize, to_slot: usize },
+    Note       { player: String, card_id: usize, text: String },
+    Kick       { name: String },
+}
+
+impl LoggedAction {
+    pub fn of(action: &Action, game_state: &GameState) -> Self {
+        let name = |seat| game_state.name_of(seat).to_owned();
+        match *action {
+            Action::Discard    { player, card_id }        => LoggedAction::Discard { player: name(player), card_id: card_id },
+            Action::Play       { player, card_id }        => LoggedAction::Play { player: name(player), card_id: card_id },
+            Action::HintColor  { player, target, color }  => {
+                LoggedAction::HintColor { player: name(player), target: name(target), color: color }
+            }
+            Action::HintNumber { player, target, number } => {
+                LoggedAction::HintNumber { player: name(player), target: name(target), number: number }
+            }
+        }
+    }
+
+    /// The game action to replay, if this is one and all of its players are seated in `game_state`.
+    pub fn action(&self, game_state: &GameState) -> Option<Action> {
+        let seat = |name: &str| game_state.seat_of(name);
+        match *self {
+            LoggedAction::Join { .. } | LoggedAction::Seats { .. } | LoggedAction::Start |
+            LoggedAction::Move { .. } | LoggedAction::Note { .. } | LoggedAction::Kick { .. } => None,
+            LoggedAction::Discard    { ref player, card_id } => seat(player).map(|p| Action::Discard { player: p, card_id: card_id }),
+            LoggedAction::Play       { ref player, card_id } => seat(player).map(|p| Action::Play { player: p, card_id: card_id }),
+            LoggedAction::HintColor  { ref player, ref target, color } => {
+                seat(player).and_then(|p| seat(target).map(|t| Action::HintColor { player: p, target: t, color: color }))
+            }
+            LoggedAction::HintNumber { ref player, ref target, number } => {
+                seat(player).and_then(|p| seat(target).map(|t| Action::HintNumber { player: p, target: t, number: number }))
+            }
+        }
+    }
+}
+
+pub struct SavedGame {
+    pub id:      String,
+    pub record:  GameRecord,
+    pub actions: Vec<LoggedAction>,
+}
+
+/// Append-only game storage. Every game is kept in its own file inside the data directory:
+/// the first line holds the `GameRecord`, every following line one `LoggedAction`.
+/// Finished games are renamed so they will not be picked up again on startup.
+pub struct GameStore {
+    dir: PathBuf,
+}
+
+impl GameStore {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        info!("Opening game storage in {}.", dir.display());
+        try!(fs::create_dir_all(dir));
+        Ok(GameStore {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn new_game_id() -> String {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        format!("{:012}-{:08x}", secs, rand::random::<u32>())
+    }
+
+    pub fn create(&self, id: &str, record: &GameRecord) -> io::Result<Void> {
+        debug!("Creating storage for game {}.", id);
+        let mut file = try!(File::create(self.path(id, RUNNING_EXT)));
+        try!(writeln!(file, "{}", json::encode(record).expect(CATASTROPHIC_FUCKUP)));
+        file.sync_data()
+    }
+
+    pub fn append(&self, id: &str, action: &LoggedAction) -> io::Result<Void> {
+        debug!("Persisting action for game {}.", id);
+        let mut file = try!(OpenOptions::new().append(true).open(self.path(id, RUNNING_EXT)));
+        try!(writeln!(file, "{}", json::encode(action).expect(CATASTROPHIC_FUCKUP)));
+        file.sync_data()
+    }
+
+    pub fn finish(&self, id: &str) -> io::Result<Void> {
+        info!("Marking game {} as finished.", id);
+        fs::rename(self.path(id, RUNNING_EXT), self.path(id, FINISHED_EXT))
+    }
+
+    /// Loads the most recently created game that has not been finished yet, if any.
+    pub fn load_unfinished(&self) -> io::Result<Option<SavedGame>> {
+        let mut ids = Vec::new();
+        for entry in try!(fs::read_dir(&self.dir)) {
+            let path = try!(entry).path();
+            if path.extension().map_or(false, |ext| ext == RUNNING_EXT) {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(stem.to_owned());
+                }
+            }
+        }
+        ids.sort();
+
+        match ids.pop() {
+            Some(id) => {
+                for stale in &ids {
+                    warn!("Ignoring older unfinished game {}.", stale);
+                }
+                self.load(id).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn load(&self, id: String) -> io::Result<SavedGame> {
+        info!("Loading unfinished game {}.", id);
+        let file = try!(File::open(self.path(&id, RUNNING_EXT)));
+        let mut lines = BufReader::new(file).lines();
+
+        let record = match lines.next() {
+            Some(line) => try!(json::decode::<GameRecord>(&try!(line)).map_err(invalid_data)),
+            None       => return Err(invalid_data("missing game record")),
+        };
+
+        let mut actions = Vec::new();
+        for line in lines {
+            match json::decode::<LoggedAction>(&try!(line)) {
+                Ok(action) => actions.push(action),
+                Err(e)     => {
+                    // only the last write can have been interrupted by a crash
+                    warn!("Dropping unreadable trailing action of game {}: {}.", id, e);
+                    break;
+                }
+            }
+        }
+        debug!("Loaded {} actions for game {}.", actions.len(), id);
+
+        Ok(SavedGame {
+            id:      id,
+            record:  record,
+            actions: actions,
+        })
+    }
+
+    fn path(&self, id: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, ext))
+    }
+}
+
+fn invalid_data<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
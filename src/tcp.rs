@@ -0,0 +1,113 @@
+use ws;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+use server::Server;
+use connection::{Connection, Peer};
+use responses::error_messages::MSG_TO_TXT_ERROR;
+use wire;
+use game_state::Void;
+
+/// Longest request line that is accepted, a client sending a longer one is disconnected.
+const MAX_LINE_BYTES: usize = 64 * 1024;
+/// Messages queued for a client before it counts as not reading and is disconnected.
+const OUTGOING_QUEUE: usize = 256;
+const WRITE_TIMEOUT:  u64   = 10;
+
+/// Accepts clients speaking newline-delimited json over plain TCP, e.g. bots without a websocket library.
+/// Every client is served on its own thread, feeding the same server as the websocket clients.
+pub fn listen(addr: &str, server: Arc<Mutex<Server>>) -> io::Result<Void> {
+    let listener = try!(TcpListener::bind(addr));
+    info!("Start listening for incoming TCP connections on {}.", addr);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let server = server.clone();
+                    thread::spawn(move || serve(stream, server));
+                }
+                Err(e) => error!("Could not accept TCP connection: {}.", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn serve(stream: TcpStream, server: Arc<Mutex<Server>>) {
+    let (mut reader, writer) = match (stream.try_clone(), stream.try_clone()) {
+        (Ok(reader), Ok(writer))  => (BufReader::new(reader), writer),
+        (Err(e), _) | (_, Err(e)) => return error!("Could not set up TCP connection: {}.", e),
+    };
+    if let Err(e) = writer.set_write_timeout(Some(Duration::from_secs(WRITE_TIMEOUT))) {
+        return error!("Could not set up TCP connection: {}.", e);
+    }
+    let (lines, outgoing) = mpsc::sync_channel(OUTGOING_QUEUE);
+    let con = Connection::new(Connection::next_id(), Peer::Tcp { lines: lines, stream: Arc::new(stream) });
+    info!("TCP client connected as Connection {}.", con.id);
+    let id = con.id;
+    thread::spawn(move || write_lines(writer, outgoing, id));
+    server.lock().unwrap().add_connection(&con);
+
+    loop {
+        let line = match read_line(&mut reader) {
+            Ok(Some(line)) => line,
+            Ok(None)       => break,
+            Err(e)         => {
+                error!("Could not read from Connection {}: {}.", con.id, e);
+                if e.kind() == io::ErrorKind::InvalidData {
+                    let details = format!("Lines are limited to {} bytes of UTF-8.", MAX_LINE_BYTES);
+                    server.lock().unwrap().answer_with_error_msg(MSG_TO_TXT_ERROR, Some(&details), &con).unwrap_or(());
+                }
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Err(e) = handle_line(&line, &con, &server) {
+            error!("Could not answer Connection {}: {}.", con.id, e);
+            break;
+        }
+    }
+
+    info!("TCP client on Connection {} disconnected.", con.id);
+    server.lock().unwrap().remove_connection(&con);
+}
+
+/// Reads up to the next newline, `None` once the client has closed the connection. A line that
+/// is too long or no UTF-8 fails with `InvalidData`.
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = try!(reader.by_ref().take(MAX_LINE_BYTES as u64 + 1).read_until(b'\n', &mut line));
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.len() > MAX_LINE_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    String::from_utf8(line).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes the queued messages of a client until it is removed from the server. A client that
+/// does not take its messages within the write timeout is disconnected.
+fn write_lines(mut stream: TcpStream, outgoing: Receiver<String>, id: usize) {
+    for line in outgoing {
+        if let Err(e) = stream.write_all(line.as_bytes()) {
+            error!("Could not write to Connection {}: {}.", id, e);
+            stream.shutdown(Shutdown::Both).unwrap_or(());
+            break;
+        }
+    }
+}
+
+fn handle_line(line: &str, con: &Connection, server: &Mutex<Server>) -> ws::Result<Void> {
+    let mut server = server.lock().unwrap();
+    match wire::decode_text(line) {
+        Ok(req)                     => server.handle_req(&req, &con),
+        Err((explanation, details)) => server.answer_with_error_msg(explanation, Some(&details), &con),
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(feature = "tls")]
+use openssl::ssl::{SslContext, SslMethod};
+#[cfg(feature = "tls")]
+use openssl::x509::X509FileType;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::path::Path;
+use config::Config;
+
+/// Certificate and private key the websocket listener encrypts its connections with.
+#[cfg(feature = "tls")]
+pub type TlsContext = Arc<SslContext>;
+
+#[cfg(not(feature = "tls"))]
+pub type TlsContext = ();
+
+/// Serving `wss://` requires both a certificate and a key, configuring only one of them is an error.
+pub fn from_config(config: &Config) -> Result<Option<TlsContext>, String> {
+    match (config.tls_cert.as_ref(), config.tls_key.as_ref()) {
+        (Some(cert), Some(key)) => load(cert, key).map(Some),
+        (None, None)            => Ok(None),
+        _                       => Err("both a certificate and a private key are needed".to_owned()),
+    }
+}
+
+#[cfg(feature = "tls")]
+fn load(cert: &Path, key: &Path) -> Result<TlsContext, String> {
+    info!("Loading TLS certificate {} and key {}.", cert.display(), key.display());
+    let mut context = try!(SslContext::new(SslMethod::Sslv23).map_err(|e| e.to_string()));
+    try!(context.set_certificate_chain_file(cert, X509FileType::PEM).map_err(|e| format!("invalid certificate: {}", e)));
+    try!(context.set_private_key_file(key, X509FileType::PEM).map_err(|e| format!("invalid private key: {}", e)));
+    try!(context.check_private_key().map_err(|e| format!("private key does not match certificate: {}", e)));
+    Ok(Arc::new(context))
+}
+
+#[cfg(not(feature = "tls"))]
+fn load(_: &Path, _: &Path) -> Result<TlsContext, String> {
+    Err("the server was built without the tls feature".to_owned())
+}
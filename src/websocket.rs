@@ -0,0 +1,87 @@
+use ws::{Handler, Message, Sender, Handshake, Request, Response, Result, CloseCode, Error};
+#[cfg(feature = "tls")]
+use ws::ErrorKind;
+#[cfg(feature = "tls")]
+use openssl::ssl::Ssl;
+use std::sync::{Arc, Mutex};
+use server::Server;
+use connection::{Connection, Peer};
+use wire::{self, WireFormat};
+use tls::TlsContext;
+use game_state::Void;
+
+pub struct WebsocketHandler {
+    id:     usize,
+    out:    Sender,
+    format: WireFormat,
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    tls:    Option<TlsContext>,
+    server: Arc<Mutex<Server>>
+}
+
+impl Handler for WebsocketHandler {
+    fn on_request(&mut self, req: &Request) -> Result<Response> {
+        let mut res = try!(Response::from_request(req));
+        if let Some(format) = WireFormat::negotiate(&try!(req.protocols())) {
+            info!("Connection {} uses wire format {:?}.", self.id, format);
+            res.set_protocol(format.subprotocol());
+            self.format = format;
+        }
+        Ok(res)
+    }
+
+    fn on_open(&mut self, _: Handshake) -> Result<Void> {
+        info!("On Open.");
+        self.server.lock().unwrap().add_connection(&self.connection());
+        Ok(())
+    }
+
+    fn on_close(&mut self, code: CloseCode, reason: &str) {
+        info!("On Close with CloseCode '{:?}' and Reason '{}'.", code ,reason);
+        self.server.lock().unwrap().remove_connection(&self.connection());
+    }
+
+    fn on_error(&mut self, err: Error) {
+        error!("On Error with Error '{}'.", err);
+    }
+
+    fn on_message(&mut self, msg: Message) -> Result<Void> {
+        info!("On message.");
+        let con = self.connection();
+        let mut server = self.server.lock().unwrap();
+        match wire::decode(&msg) {
+            Ok(req)                     => server.handle_req(&req, &con),
+            Err((explanation, details)) => server.answer_with_error_msg(explanation, Some(&details), &con),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn build_ssl(&mut self) -> Result<Ssl> {
+        match self.tls {
+            Some(ref context) => Ssl::new(context).map_err(Error::from),
+            None              => Err(Error::new(ErrorKind::Internal, "TLS is not configured.")),
+        }
+    }
+}
+
+impl WebsocketHandler {
+
+    pub fn new(out: Sender, tls: Option<TlsContext>, server: Arc<Mutex<Server>>) -> Self {
+        let id = Connection::next_id();
+        debug!("Creating new websocket handler for Connection {}", id);
+        WebsocketHandler {
+            id:     id,
+            out:    out,
+            format: Default::default(),
+            tls:    tls,
+            server: server,
+        }
+    }
+
+    fn connection(&self) -> Connection {
+        Connection::new(self.id, Peer::Websocket {
+            out:    self.out.clone(),
+            format: self.format,
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use rustc_serialize::json::Json;
+use ws::Message;
+use msgpack;
+use responses::error_messages::{MSG_TO_TXT_ERROR, UNABLE_TO_GET_MSG_TYPE};
+
+const JSON_SUBPROTOCOL:    &'static str = "hanabi.json";
+const MSGPACK_SUBPROTOCOL: &'static str = "hanabi.msgpack";
+
+/// The encoding of the messages sent to a client, negotiated per connection via websocket subprotocol.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WireFormat {
+    Json,
+    MessagePack,
+}
+
+impl Default for WireFormat {
+    fn default() -> Self {
+        WireFormat::Json
+    }
+}
+
+impl WireFormat {
+    /// Picks the first subprotocol offered by the client that the server knows.
+    pub fn negotiate(subprotocols: &[&str]) -> Option<Self> {
+        subprotocols.iter().filter_map(|p| match *p {
+            JSON_SUBPROTOCOL    => Some(WireFormat::Json),
+            MSGPACK_SUBPROTOCOL => Some(WireFormat::MessagePack),
+            _                   => None,
+        }).next()
+    }
+
+    pub fn subprotocol(&self) -> &'static str {
+        match *self {
+            WireFormat::Json        => JSON_SUBPROTOCOL,
+            WireFormat::MessagePack => MSGPACK_SUBPROTOCOL,
+        }
+    }
+
+    pub fn encode(&self, msg: &Json) -> Message {
+        match *self {
+            WireFormat::Json        => Message::text(msg.to_string()),
+            WireFormat::MessagePack => Message::binary(msgpack::encode(msg)),
+        }
+    }
+}
+
+/// Text frames are always read as json and binary frames as MessagePack, whatever was negotiated.
+pub fn decode(msg: &Message) -> Result<Json, (&'static str, String)> {
+    match *msg {
+        Message::Text(ref txt)    => decode_text(txt),
+        Message::Binary(ref data) => msgpack::decode(data).map_err(|e| (MSG_TO_TXT_ERROR, e.to_string())),
+    }
+}
+
+pub fn decode_text(txt: &str) -> Result<Json, (&'static str, String)> {
+    Json::from_str(txt).map_err(|e| (UNABLE_TO_GET_MSG_TYPE, e.to_string()))
+}